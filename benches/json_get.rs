@@ -0,0 +1,102 @@
+//! Compares reading a value with `json_get`, which goes through a fresh `String`, against
+//! [`JsonRead::json_get_into`](redis_utils::converters::JsonRead) moving the reply into one buffer,
+//! both into an owned value and into one borrowing from the buffer.
+//!
+//! The connection is an in-process stand-in replying with the same json to every `GET`, so only
+//! the decoding is measured, not the round trip to redis.
+//!
+//! Run with `cargo bench --bench json_get`.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use redis::aio::ConnectionLike;
+use redis::{Cmd, Pipeline, RedisFuture, Value};
+use redis_utils::converters::{JsonGet, JsonRead};
+use serde::Deserialize;
+use std::hint::black_box;
+
+#[derive(Deserialize)]
+struct Owned {
+    name: String,
+    tags: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct Borrowed<'a> {
+    name: &'a str,
+    #[serde(borrow)]
+    tags: Vec<&'a str>,
+}
+
+/// Replies `json` to every command.
+struct Fixed {
+    json: Vec<u8>,
+}
+
+impl ConnectionLike for Fixed {
+    fn req_packed_command<'a>(&'a mut self, _: &'a Cmd) -> RedisFuture<'a, Value> {
+        Box::pin(async move { Ok(Value::Data(self.json.clone())) })
+    }
+
+    fn req_packed_commands<'a>(
+        &'a mut self,
+        _: &'a Pipeline,
+        _: usize,
+        _: usize,
+    ) -> RedisFuture<'a, Vec<Value>> {
+        unreachable!()
+    }
+
+    fn get_db(&self) -> i64 {
+        0
+    }
+}
+
+fn json_get(c: &mut Criterion) {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+
+    let mut group = c.benchmark_group("json_get");
+    for tags in [4, 64, 1024] {
+        let tags: Vec<String> = (0..tags).map(|tag| format!("tag-{}", tag)).collect();
+        let json = format!(r#"{{"name":"article","tags":{:?}}}"#, tags);
+        let mut con = Fixed {
+            json: json.into_bytes(),
+        };
+        let mut buf = Vec::new();
+
+        group.bench_function(BenchmarkId::new("json_get", tags.len()), |b| {
+            b.iter(|| {
+                runtime.block_on(async {
+                    if let Ok(val) = JsonGet::<Owned>::json_get(&mut con, "key").await {
+                        black_box((val.name, val.tags));
+                    }
+                })
+            })
+        });
+
+        group.bench_function(BenchmarkId::new("into, owned", tags.len()), |b| {
+            b.iter(|| {
+                runtime.block_on(async {
+                    if let Ok(val) = con.json_get_into::<_, Owned>("key", &mut buf).await {
+                        black_box((val.name, val.tags));
+                    }
+                })
+            })
+        });
+
+        group.bench_function(BenchmarkId::new("into, borrowed", tags.len()), |b| {
+            b.iter(|| {
+                runtime.block_on(async {
+                    if let Ok(val) = con.json_get_into::<_, Borrowed>("key", &mut buf).await {
+                        black_box((val.name, val.tags));
+                    }
+                })
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, json_get);
+criterion_main!(benches);
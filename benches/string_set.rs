@@ -0,0 +1,40 @@
+//! Compares queueing a string with `json_set`, which json encodes (quotes and escapes) it, against
+//! setting it raw like [`JsonSet::set_raw_string`](redis_utils::converters::JsonSet) does.
+//!
+//! Run with `cargo bench --bench string_set`.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use redis_utils::converters::PipelineJsonSet;
+use redis_utils::TxError;
+use std::hint::black_box;
+
+fn string_set(c: &mut Criterion) {
+    let mut group = c.benchmark_group("string_set");
+    for size in [16, 1024, 64 * 1024] {
+        // quotes and backslashes are what json has to escape
+        let val: String = "ab\"c\\".chars().cycle().take(size).collect();
+        group.throughput(Throughput::Bytes(size as u64));
+
+        group.bench_with_input(BenchmarkId::new("json", size), &val, |b, val| {
+            b.iter(|| {
+                let mut pipe = redis::pipe();
+                let queued: Result<_, TxError<()>> = pipe.json_set("key", black_box(val));
+                if let Ok(pipe) = queued {
+                    black_box(pipe.get_packed_pipeline());
+                }
+            })
+        });
+
+        group.bench_with_input(BenchmarkId::new("raw", size), &val, |b, val| {
+            b.iter(|| {
+                let mut pipe = redis::pipe();
+                pipe.set("key", black_box(val));
+                black_box(pipe.get_packed_pipeline());
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, string_set);
+criterion_main!(benches);
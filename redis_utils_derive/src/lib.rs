@@ -0,0 +1,174 @@
+//! Derive macros for [redis_utils](https://docs.rs/redis_utils), use them through its `derive`
+//! feature rather than depending on this crate directly.
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, LitStr, Path};
+
+/// Implements `redis_utils::stored::JsonStored` for a type, see its documentation.
+///
+/// + `#[redis(prefix = "user")]` sets the key prefix, it defaults to the lowercased type name.
+/// + `#[redis(crate = "ru")]` points the generated code at a renamed `redis_utils` dependency.
+#[proc_macro_derive(RedisJson, attributes(redis))]
+pub fn derive_redis_json(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand(input) {
+        Ok(expanded) => expanded.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn expand(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let mut prefix = input.ident.to_string().to_lowercase();
+    let mut krate: Path = syn::parse_quote!(::redis_utils);
+
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("redis"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("prefix") {
+                prefix = meta.value()?.parse::<LitStr>()?.value();
+                Ok(())
+            } else if meta.path.is_ident("crate") {
+                krate = meta.value()?.parse::<LitStr>()?.parse()?;
+                Ok(())
+            } else {
+                Err(meta.error("expected `prefix` or `crate`"))
+            }
+        })?;
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics #krate::stored::JsonStored for #name #ty_generics #where_clause {
+            const PREFIX: &'static str = #prefix;
+        }
+    })
+}
+
+/// Implements `redis_utils::enums::RedisEnum` for a fieldless enum, see its documentation.
+///
+/// + `#[redis(repr = "int")]` stores the discriminants instead of the names.
+/// + `#[redis(rename = "on")]` on a variant stores it under another name.
+/// + `#[redis(crate = "ru")]` points the generated code at a renamed `redis_utils` dependency.
+#[proc_macro_derive(RedisEnum, attributes(redis))]
+pub fn derive_redis_enum(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand_enum(input) {
+        Ok(expanded) => expanded.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn expand_enum(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let mut int = false;
+    let mut krate: Path = syn::parse_quote!(::redis_utils);
+
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("redis"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("repr") {
+                let repr = meta.value()?.parse::<LitStr>()?;
+                match repr.value().as_str() {
+                    "int" => int = true,
+                    "name" => int = false,
+                    _ => return Err(syn::Error::new(repr.span(), "expected `int` or `name`")),
+                }
+                Ok(())
+            } else if meta.path.is_ident("crate") {
+                krate = meta.value()?.parse::<LitStr>()?.parse()?;
+                Ok(())
+            } else {
+                Err(meta.error("expected `repr` or `crate`"))
+            }
+        })?;
+    }
+
+    let variants = match &input.data {
+        Data::Enum(data) => &data.variants,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "RedisEnum can only be derived for enums",
+            ))
+        }
+    };
+
+    let mut idents = vec![];
+    let mut names = vec![];
+    for variant in variants {
+        if !matches!(variant.fields, Fields::Unit) {
+            return Err(syn::Error::new_spanned(
+                variant,
+                "RedisEnum can only be derived for enums without fields",
+            ));
+        }
+
+        let mut name = variant.ident.to_string();
+        for attr in variant
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("redis"))
+        {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    name = meta.value()?.parse::<LitStr>()?.value();
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `rename`"))
+                }
+            })?;
+        }
+        idents.push(&variant.ident);
+        names.push(name);
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let (to_stored, from_stored) = match int {
+        true => (
+            quote! {
+                match self {
+                    #(Self::#idents => ::std::string::ToString::to_string(&(Self::#idents as i64)).into(),)*
+                }
+            },
+            quote! {
+                let stored: i64 = stored.parse().ok()?;
+                #(if stored == Self::#idents as i64 {
+                    return ::std::option::Option::Some(Self::#idents);
+                })*
+                ::std::option::Option::None
+            },
+        ),
+        false => (
+            quote! {
+                match self {
+                    #(Self::#idents => ::std::borrow::Cow::Borrowed(#names),)*
+                }
+            },
+            quote! {
+                match stored {
+                    #(#names => ::std::option::Option::Some(Self::#idents),)*
+                    _ => ::std::option::Option::None,
+                }
+            },
+        ),
+    };
+    Ok(quote! {
+        impl #impl_generics #krate::enums::RedisEnum for #name #ty_generics #where_clause {
+            fn to_stored(&self) -> ::std::borrow::Cow<'static, str> {
+                #to_stored
+            }
+
+            fn from_stored(stored: &str) -> ::std::option::Option<Self> {
+                #from_stored
+            }
+        }
+    })
+}
@@ -0,0 +1,175 @@
+//! Reads of json values through different commands, in one round trip.
+//!
+//! [`JsonGet::json_mget`](crate::converters::JsonGet::json_mget) reads string keys only and hands
+//! values back by position, all of one type. A [`JsonBatch`] queues reads of any command replying
+//! with a json string (`GET`, `HGET`, `LINDEX`, ...), each with its own type, pipelines them and
+//! hands every value back through the [`BatchItem`] its read returned.
+
+use crate::converters::JsonGetError;
+use redis::aio::ConnectionLike;
+use redis::{Cmd, Pipeline, ToRedisArgs};
+use serde::de::DeserializeOwned;
+use std::any::Any;
+use std::marker::PhantomData;
+
+type Decoded = Result<Option<Box<dyn Any + Send>>, serde_json::Error>;
+
+/// ```
+/// extern crate redis_utils;
+/// extern crate redis;
+///
+/// use serde::Deserialize;
+/// use redis::Value;
+/// use redis_utils::batch::JsonBatch;
+/// use redis_utils::converters::JsonGetError;
+/// # use redis_utils::mock::MockConnection;
+///
+/// #[derive(Debug, PartialEq, Deserialize)]
+/// struct User {
+///     name: String,
+/// }
+///
+/// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+/// # let mut con = MockConnection::new()
+/// #     .with_value("user:1", r#"{"name":"ada"}"#)
+/// #     .reply("HGET", Value::Data(b"not json".to_vec()))
+/// #     .reply("LINDEX", Value::Nil);
+/// let mut batch = JsonBatch::new();
+/// let user = batch.get::<User, _>("user:1");
+/// let theme = batch.hget::<String, _, _>("settings", "theme");
+/// let latest = batch.lindex::<u64, _>("scores", -1);
+///
+/// let mut results = batch.run(&mut con).await.unwrap();
+/// assert_eq!(results.take(user).unwrap(), Some(User { name: "ada".to_string() }));
+/// // a value that doesn't deserialize only fails its own read
+/// assert!(matches!(results.take(theme), Err(JsonGetError::Serialization(_))));
+/// assert_eq!(results.take(latest).unwrap(), None);
+/// # });
+/// ```
+#[derive(Default)]
+pub struct JsonBatch {
+    pipeline: Pipeline,
+    decoders: Vec<fn(&str) -> Decoded>,
+    fail_fast: bool,
+}
+
+/// The handle of a read queued on a [`JsonBatch`], to take its value out of the results with.
+#[must_use = "the value of the read can only be taken with its item"]
+pub struct BatchItem<Val> {
+    index: usize,
+    val: PhantomData<fn() -> Val>,
+}
+
+/// The values read by a [`JsonBatch`].
+pub struct BatchResults {
+    decoded: Vec<Option<Decoded>>,
+}
+
+fn decode<Val: DeserializeOwned + Send + 'static>(json: &str) -> Decoded {
+    let val: Val = serde_json::from_str(json)?;
+    Ok(Some(Box::new(val)))
+}
+
+impl JsonBatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fails the whole batch with the first value that doesn't deserialize, instead of only the
+    /// read it belongs to.
+    pub fn fail_fast(mut self, fail_fast: bool) -> Self {
+        self.fail_fast = fail_fast;
+        self
+    }
+
+    /// Queues `cmd`, any command replying with a json string or nil.
+    pub fn cmd<Val: DeserializeOwned + Send + 'static>(&mut self, cmd: &Cmd) -> BatchItem<Val> {
+        self.pipeline.add_command(cmd.clone());
+        self.decoders.push(decode::<Val>);
+        BatchItem {
+            index: self.decoders.len() - 1,
+            val: PhantomData,
+        }
+    }
+
+    /// Queues a `GET key`.
+    pub fn get<Val, Key>(&mut self, key: Key) -> BatchItem<Val>
+    where
+        Val: DeserializeOwned + Send + 'static,
+        Key: ToRedisArgs,
+    {
+        self.cmd(redis::cmd("GET").arg(key))
+    }
+
+    /// Queues a `HGET key field`.
+    pub fn hget<Val, Key, Field>(&mut self, key: Key, field: Field) -> BatchItem<Val>
+    where
+        Val: DeserializeOwned + Send + 'static,
+        Key: ToRedisArgs,
+        Field: ToRedisArgs,
+    {
+        self.cmd(redis::cmd("HGET").arg(key).arg(field))
+    }
+
+    /// Queues a `LINDEX key index`.
+    pub fn lindex<Val, Key>(&mut self, key: Key, index: isize) -> BatchItem<Val>
+    where
+        Val: DeserializeOwned + Send + 'static,
+        Key: ToRedisArgs,
+    {
+        self.cmd(redis::cmd("LINDEX").arg(key).arg(index))
+    }
+
+    /// Sends every read in one pipeline and deserializes their replies. A redis error, say a
+    /// `WRONGTYPE`, fails the whole batch.
+    pub async fn run<C>(self, con: &mut C) -> Result<BatchResults, JsonGetError>
+    where
+        C: ConnectionLike,
+    {
+        if self.decoders.is_empty() {
+            return Ok(BatchResults { decoded: vec![] });
+        }
+
+        let replies: Vec<Option<String>> = self.pipeline.query_async(con).await?;
+
+        let mut decoded = Vec::with_capacity(replies.len());
+        for (reply, decoder) in replies.into_iter().zip(self.decoders) {
+            let val = match reply {
+                Some(json) => decoder(&json),
+                None => Ok(None),
+            };
+            match val {
+                Err(err) if self.fail_fast => return Err(err.into()),
+                val => decoded.push(Some(val)),
+            }
+        }
+        Ok(BatchResults { decoded })
+    }
+}
+
+impl BatchResults {
+    /// The value read for `item`, `None` if there was nothing to read (a missing key, field or
+    /// index).
+    ///
+    /// # Panics
+    ///
+    /// If `item` was queued on another batch.
+    pub fn take<Val: 'static>(
+        &mut self,
+        item: BatchItem<Val>,
+    ) -> Result<Option<Val>, JsonGetError> {
+        let decoded = self
+            .decoded
+            .get_mut(item.index)
+            .and_then(Option::take)
+            .expect("the item was queued on another batch");
+
+        match decoded? {
+            Some(val) => match val.downcast::<Val>() {
+                Ok(val) => Ok(Some(*val)),
+                Err(_) => panic!("the item was queued on another batch"),
+            },
+            None => Ok(None),
+        }
+    }
+}
@@ -0,0 +1,243 @@
+//! A client-side cache for hot `json_get` reads, invalidated by redis itself.
+//!
+//! [`CachedJsonGet`] turns on [client-side caching](https://redis.io/docs/manual/client-side-caching/)
+//! (`CLIENT TRACKING`, redis 6+) for the connection it wraps: every key read through it is tracked
+//! by the server, and deserialized values are served from an in-process map until redis reports the
+//! key as changed. Invalidations are requested in the RESP2 redirect mode, which redis-rs supports:
+//! a second connection, subscribed to `__redis__:invalidate`, receives them and evicts the keys in
+//! a background task.
+//!
+//! A value fetched while an invalidation was on its way is never cached, and if the invalidation
+//! connection drops, the cache is emptied and every read goes back to `GET`.
+//!
+//! Like any client-side cache, a read right after another client's write can still see the old
+//! value until the invalidation arrives. Writes made through [`CachedJsonGet::connection`]
+//! invalidate the keys they touch too.
+//!
+//! Enable the `client-cache` feature to use it.
+
+use crate::converters::JsonGetError;
+use futures_util::pin_mut;
+use futures_util::stream::{Stream, StreamExt};
+use redis::aio::ConnectionLike;
+use redis::{Msg, RedisResult};
+use serde::de::DeserializeOwned;
+use std::any::Any;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use tokio::task::JoinHandle;
+
+/// The channel redis publishes invalidations of tracked keys on.
+const INVALIDATE_CHANNEL: &str = "__redis__:invalidate";
+
+/// ```no_run
+/// extern crate redis_utils;
+/// extern crate redis;
+///
+/// use serde::{Deserialize, Serialize};
+/// use redis::RedisResult;
+/// use redis_utils::cache::CachedJsonGet;
+///
+/// #[derive(Clone, Serialize, Deserialize)]
+/// struct Settings {
+///     theme: String,
+/// }
+///
+/// async fn cache_demo() -> RedisResult<()> {
+///     let client = redis::Client::open("redis://127.0.0.1/")?;
+///     let con = client.get_async_connection().await?;
+///     let mut cached = CachedJsonGet::new(&client, con).await?;
+///
+///     // one round trip, then served from memory until `settings` changes
+///     for _ in 0..100 {
+///         let settings: Settings = cached.json_get("settings").await.unwrap();
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+pub struct CachedJsonGet<C> {
+    con: C,
+    cache: Arc<Cache>,
+    listener: JoinHandle<()>,
+}
+
+#[derive(Default)]
+struct Cache {
+    entries: Mutex<HashMap<String, Entry>>,
+    // bumped on every invalidation, a read only caches its value if none came in meanwhile
+    generation: AtomicU64,
+    listening: AtomicBool,
+}
+
+impl Cache {
+    fn entries(&self) -> MutexGuard<'_, HashMap<String, Entry>> {
+        self.entries.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// The json stored under a key, and its value decoded as the type it was last read as.
+struct Entry {
+    json: Option<String>,
+    decoded: Option<Arc<dyn Any + Send + Sync>>,
+}
+
+impl Entry {
+    fn decode<Val>(&mut self) -> Result<Option<Arc<Val>>, serde_json::Error>
+    where
+        Val: DeserializeOwned + Send + Sync + 'static,
+    {
+        let decoded = self.decoded.clone().map(|val| val.downcast::<Val>());
+        if let Some(Ok(val)) = decoded {
+            return Ok(Some(val));
+        }
+
+        let json = match &self.json {
+            Some(json) => json,
+            None => return Ok(None),
+        };
+
+        let val: Arc<Val> = Arc::new(serde_json::from_str(json)?);
+        self.decoded = Some(Arc::clone(&val) as Arc<dyn Any + Send + Sync>);
+        Ok(Some(val))
+    }
+}
+
+impl<C> CachedJsonGet<C>
+where
+    C: ConnectionLike + Send,
+{
+    /// Turns on tracking for `con`, with invalidations redirected to a new connection opened from
+    /// `client`, which should point at the same server.
+    pub async fn new(client: &redis::Client, mut con: C) -> RedisResult<Self> {
+        let mut invalidations = client.get_async_connection().await?;
+        let id: i64 = redis::cmd("CLIENT")
+            .arg("ID")
+            .query_async(&mut invalidations)
+            .await?;
+
+        let mut pubsub = invalidations.into_pubsub();
+        pubsub.subscribe(INVALIDATE_CHANNEL).await?;
+
+        redis::cmd("CLIENT")
+            .arg(&["TRACKING", "ON", "REDIRECT"])
+            .arg(id)
+            .query_async::<_, ()>(&mut con)
+            .await?;
+
+        let cache = Arc::new(Cache::default());
+        cache.listening.store(true, Ordering::SeqCst);
+        let listener = tokio::spawn(listen(pubsub.into_on_message(), Arc::clone(&cache)));
+
+        Ok(CachedJsonGet {
+            con,
+            cache,
+            listener,
+        })
+    }
+
+    /// The tracked connection, for writes and anything else that shouldn't be cached.
+    pub fn connection(&mut self) -> &mut C {
+        &mut self.con
+    }
+
+    /// [`JsonGet::json_get`](crate::converters::JsonGet::json_get), served from the cache when
+    /// the key hasn't changed since it was last read.
+    pub async fn json_get<Val>(&mut self, key: &str) -> Result<Val, JsonGetError>
+    where
+        Val: DeserializeOwned + Clone + Send + Sync + 'static,
+    {
+        Ok(Val::clone(&*self.json_get_arc(key).await?))
+    }
+
+    /// [`CachedJsonGet::json_get`] handing out the cached value itself, every reader of the key
+    /// shares one `Arc` until the key changes. `Val` doesn't need to be `Clone`.
+    pub async fn json_get_arc<Val>(&mut self, key: &str) -> Result<Arc<Val>, JsonGetError>
+    where
+        Val: DeserializeOwned + Send + Sync + 'static,
+    {
+        self.lookup(key)
+            .await?
+            .ok_or_else(|| JsonGetError::missing(&key))
+    }
+
+    /// [`JsonGet::maybe_json_get`](crate::converters::JsonGet::maybe_json_get), served from the
+    /// cache when the key hasn't changed since it was last read. Missing keys are cached as well.
+    pub async fn maybe_json_get<Val>(&mut self, key: &str) -> Result<Option<Val>, JsonGetError>
+    where
+        Val: DeserializeOwned + Clone + Send + Sync + 'static,
+    {
+        let val = self.lookup::<Val>(key).await?;
+        Ok(val.map(|val| Val::clone(&*val)))
+    }
+
+    async fn lookup<Val>(&mut self, key: &str) -> Result<Option<Arc<Val>>, JsonGetError>
+    where
+        Val: DeserializeOwned + Send + Sync + 'static,
+    {
+        if self.cache.listening.load(Ordering::SeqCst) {
+            if let Some(entry) = self.cache.entries().get_mut(key) {
+                return Ok(entry.decode()?);
+            }
+        }
+
+        let generation = self.cache.generation.load(Ordering::SeqCst);
+        let json: Option<String> = redis::cmd("GET")
+            .arg(key)
+            .query_async(&mut self.con)
+            .await?;
+
+        let mut entry = Entry {
+            json,
+            decoded: None,
+        };
+        let val = entry.decode()?;
+
+        let mut entries = self.cache.entries();
+        if self.cache.listening.load(Ordering::SeqCst)
+            && self.cache.generation.load(Ordering::SeqCst) == generation
+        {
+            entries.insert(key.to_owned(), entry);
+        }
+
+        Ok(val)
+    }
+
+    /// Drops every cached value, the next reads go to redis.
+    pub fn clear(&self) {
+        self.cache.generation.fetch_add(1, Ordering::SeqCst);
+        self.cache.entries().clear();
+    }
+}
+
+impl<C> Drop for CachedJsonGet<C> {
+    fn drop(&mut self) {
+        self.listener.abort();
+    }
+}
+
+/// Evicts the keys of each invalidation as it comes in, a nil payload (`FLUSHALL`, or the server
+/// running out of tracking memory) invalidates everything.
+async fn listen(messages: impl Stream<Item = Msg>, cache: Arc<Cache>) {
+    pin_mut!(messages);
+
+    while let Some(msg) = messages.next().await {
+        cache.generation.fetch_add(1, Ordering::SeqCst);
+
+        let mut entries = cache.entries();
+        match msg.get_payload::<Option<Vec<String>>>() {
+            Ok(Some(keys)) => {
+                for key in keys {
+                    entries.remove(&key);
+                }
+            }
+            _ => entries.clear(),
+        }
+    }
+
+    // without invalidations nothing cached can be trusted anymore
+    cache.listening.store(false, Ordering::SeqCst);
+    cache.generation.fetch_add(1, Ordering::SeqCst);
+    cache.entries().clear();
+}
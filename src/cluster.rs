@@ -0,0 +1,322 @@
+use crate::converters::{from_optional_json, JsonGetError};
+use crate::options::TxOptions;
+use crate::TxError;
+use async_trait::async_trait;
+use futures_util::future::try_join_all;
+use redis::aio::ConnectionLike;
+use redis::{ErrorKind, RedisError};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+const SLOT_COUNT: u16 = 16384;
+
+/// Computes the [cluster hash slot](https://redis.io/topics/cluster-spec#keys-hash-tags) of a key,
+/// honouring `{hash tags}`.
+pub fn key_slot(key: &[u8]) -> u16 {
+    let hashed = match key.iter().position(|byte| *byte == b'{') {
+        Some(open) => match key[open + 1..].iter().position(|byte| *byte == b'}') {
+            Some(close) if close > 0 => &key[open + 1..open + 1 + close],
+            _ => key,
+        },
+        None => key,
+    };
+    crc16(hashed) % SLOT_COUNT
+}
+
+/// CRC16-XMODEM, the checksum redis uses for key slots.
+fn crc16(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0;
+    for byte in data {
+        crc ^= (*byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+#[async_trait]
+pub trait JsonClusterGet<Val> {
+    async fn json_mget_cluster(
+        &mut self,
+        keys: Vec<String>,
+    ) -> Result<Vec<Option<Val>>, JsonGetError>;
+}
+
+/// ```no_run
+/// extern crate redis_utils;
+/// extern crate redis;
+///
+/// use serde::Deserialize;
+/// use redis::RedisResult;
+/// use redis_utils::cluster::JsonClusterGet;
+///
+/// #[derive(Deserialize)]
+/// struct Person {
+///     name: String,
+///     age: u8,
+/// }
+///
+/// async fn cluster_demo() -> RedisResult<()> {
+///     let mut con = redis::Client::open("redis://127.0.0.1/")?.get_multiplexed_tokio_connection().await?;
+///     let keys = vec!["person:1".to_string(), "person:2".to_string()];
+///     let people: Vec<Option<Person>> = con.json_mget_cluster(keys).await.unwrap();
+///
+///     Ok(())
+/// }
+/// ```
+#[async_trait]
+impl<C, Val> JsonClusterGet<Val> for C
+where
+    C: ConnectionLike + Clone + Send + Sync,
+    Val: DeserializeOwned,
+{
+    /// group keys by slot -> concurrent mget per slot -> deserialize it from json, in the original
+    /// order of `keys`, with `None` for missing keys
+    async fn json_mget_cluster(
+        &mut self,
+        keys: Vec<String>,
+    ) -> Result<Vec<Option<Val>>, JsonGetError> {
+        let mut slots: BTreeMap<u16, Vec<usize>> = BTreeMap::new();
+        for (index, key) in keys.iter().enumerate() {
+            slots
+                .entry(key_slot(key.as_bytes()))
+                .or_default()
+                .push(index);
+        }
+
+        let requests = slots.into_values().map(|indices| {
+            let mut con = self.clone();
+            let slot_keys: Vec<&str> = indices.iter().map(|index| keys[*index].as_str()).collect();
+            async move {
+                let strings: Vec<Option<String>> = redis::cmd("MGET")
+                    .arg(slot_keys)
+                    .query_async(&mut con)
+                    .await?;
+                Ok::<_, RedisError>((indices, strings))
+            }
+        });
+        let responses = try_join_all(requests).await?;
+
+        let mut values: Vec<Option<Val>> = (0..keys.len()).map(|_| None).collect();
+        for (indices, strings) in responses {
+            for (index, string) in indices.into_iter().zip(strings) {
+                if let Some(string) = string {
+                    values[index] = Some(serde_json::from_str(&string)?);
+                }
+            }
+        }
+        Ok(values)
+    }
+}
+
+/// Checks that every key still holds what was read (`'v'` and the value, or `'n'` for a missing
+/// key), and only then applies the writes: `'s'` sets the key, `'d'` deletes it and `'k'` keeps it.
+const CAS_SCRIPT: &str = r#"
+for i, key in ipairs(KEYS) do
+  local current = redis.call('GET', key)
+  local read = ARGV[i * 4 - 3]
+  if (read == 'n' and current) or (read == 'v' and current ~= ARGV[i * 4 - 2]) then
+    return 0
+  end
+end
+for i, key in ipairs(KEYS) do
+  local write = ARGV[i * 4 - 1]
+  if write == 's' then
+    redis.call('SET', key, ARGV[i * 4])
+  elseif write == 'd' then
+    redis.call('DEL', key)
+  end
+end
+return 1
+"#;
+
+/// A transaction for cluster connections, where `WATCH` can't be relied on: it may be routed to
+/// another node than the commands after it. [`tx!`](crate::tx) bodies are arbitrary async code and
+/// can't be turned into a script, so this runs the optimistic lock differently, the values of
+/// `keys` are read, the body computes their new values from them (`None` deletes a key), and a Lua
+/// script (`EVAL`) compare-and-sets them: it writes only if every key still holds what was read,
+/// otherwise the whole thing is re-run. Keys the body leaves as they were aren't written, keeping
+/// their expiry.
+///
+/// Scripts run atomically on one node, so every key must be in the same hash slot (share a
+/// `{hash tag}`), or the transaction fails with a `CrossSlot` `TxError::DbError` before anything
+/// is sent. Unlike `tx!`, the body can't read further keys or queue other commands, and it's
+/// synchronous. The script is sent with `EVAL` each time, as the `script` feature of redis-rs
+/// (`EVALSHA`) isn't enabled. Returns the values written.
+///
+/// `options` apply as they do to `tx!`, a failed compare-and-set counting as a watched key that
+/// changed, with the `retry_strategy` pacing the re-runs. The `namespace` is the exception: the
+/// keys are taken as they are.
+///
+/// ```no_run
+/// extern crate redis_utils;
+/// extern crate redis;
+///
+/// use std::time::Duration;
+/// use redis::RedisResult;
+/// use redis_utils::cluster::cluster_tx;
+/// use redis_utils::options::TxOptions;
+/// use redis_utils::TxError;
+///
+/// async fn transfer_demo() -> RedisResult<()> {
+///     let mut con = redis::Client::open("redis://127.0.0.1/")?.get_multiplexed_tokio_connection().await?;
+///     let keys = ["{account}:alice", "{account}:bob"];
+///     let options = TxOptions::new().timeout(Duration::from_secs(2));
+///     let tx_result: Result<Vec<Option<i64>>, TxError<&str>> = cluster_tx(&mut con, &keys, &options, |balances| {
+///         let (alice, bob) = (balances[0].unwrap_or(0), balances[1].unwrap_or(0));
+///         if alice < 10 {
+///             return Err(TxError::Abort("InsufficientFunds"));
+///         }
+///         Ok(vec![Some(alice - 10), Some(bob + 10)])
+///     })
+///     .await;
+///
+///     Ok(())
+/// }
+/// ```
+///
+/// ```
+/// # use redis::ErrorKind;
+/// use redis_utils::cluster::cluster_tx;
+/// use redis_utils::options::TxOptions;
+/// use redis_utils::TxError;
+/// # use redis_utils::mock::MockConnection;
+/// #
+/// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+/// # let mut con = MockConnection::new();
+/// let tx_result: Result<Vec<Option<i64>>, TxError<()>> =
+///     cluster_tx(&mut con, &["alice", "bob"], &TxOptions::new(), |balances| Ok(balances)).await;
+///
+/// assert!(matches!(tx_result, Err(TxError::DbError(err)) if err.kind() == ErrorKind::CrossSlot));
+/// # assert!(con.sent.is_empty());
+/// # });
+/// ```
+///
+/// A key written by another client between the read and the script every time, the
+/// `retry_strategy` gives up:
+///
+/// ```
+/// use std::time::Duration;
+/// use redis_utils::cluster::cluster_tx;
+/// use redis_utils::options::{FixedDelay, TxOptions};
+/// use redis_utils::TxError;
+/// # use redis::Value;
+/// # use redis_utils::mock::MockConnection;
+/// #
+/// # tokio::runtime::Builder::new_current_thread().enable_time().build().unwrap().block_on(async {
+/// # let mut con = MockConnection::new()
+/// #     .reply("EVAL", Value::Int(0))
+/// #     .reply("EVAL", Value::Int(0));
+/// let strategy = FixedDelay { delay: Duration::from_millis(1), retries: 1 };
+/// let options = TxOptions::new().retry_strategy(strategy);
+/// let keys = ["{counter}:hits"];
+/// let tx_result: Result<Vec<Option<u64>>, TxError<()>> = cluster_tx(&mut con, &keys, &options, |hits| {
+///     Ok(vec![Some(hits[0].unwrap_or(0) + 1)])
+/// })
+/// .await;
+///
+/// assert!(matches!(tx_result, Err(TxError::MaxRetriesExceeded { attempts: 2 })));
+/// assert_eq!(con.sent, vec!["MGET", "EVAL", "MGET", "EVAL"]);
+/// # });
+/// ```
+pub async fn cluster_tx<C, Key, Val, U, F>(
+    con: &mut C,
+    keys: &[Key],
+    options: &TxOptions,
+    mut body: F,
+) -> Result<Vec<Option<Val>>, TxError<U>>
+where
+    C: ConnectionLike + Send,
+    Key: AsRef<str>,
+    Val: Serialize + DeserializeOwned,
+    F: FnMut(Vec<Option<Val>>) -> Result<Vec<Option<Val>>, TxError<U>>,
+{
+    let keys: Vec<&str> = keys.iter().map(AsRef::as_ref).collect();
+    if keys.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let slot = key_slot(keys[0].as_bytes());
+    if keys.iter().any(|key| key_slot(key.as_bytes()) != slot) {
+        return Err(TxError::DbError(RedisError::from((
+            ErrorKind::CrossSlot,
+            "keys of a cluster transaction must share a hash slot",
+        ))));
+    }
+
+    let mut retries = 0;
+    let written = loop {
+        let attempt = attempt(con, &keys, options, &mut body).await;
+        if !options.recover(&attempt, &mut retries).await {
+            break attempt;
+        }
+    };
+    options.replicate(con, written).await
+}
+
+/// One run of [`cluster_tx`], up to a transient redis error.
+async fn attempt<C, Val, U, F>(
+    con: &mut C,
+    keys: &[&str],
+    options: &TxOptions,
+    body: &mut F,
+) -> Result<Vec<Option<Val>>, TxError<U>>
+where
+    C: ConnectionLike + Send,
+    Val: Serialize + DeserializeOwned,
+    F: FnMut(Vec<Option<Val>>) -> Result<Vec<Option<Val>>, TxError<U>>,
+{
+    options.ping(con).await?;
+
+    let mut conflicts = 0;
+    loop {
+        let mut mget = redis::cmd("MGET");
+        mget.arg(keys);
+        let read: Vec<Option<String>> = options.bounded(mget.query_async(con)).await??;
+        let mut current = Vec::with_capacity(read.len());
+        for string in &read {
+            current.push(from_optional_json(string.clone()).map_err(TxError::Serialization)?);
+        }
+
+        let written = body(current)?;
+        if written.len() != keys.len() {
+            return Err(TxError::DbError(RedisError::from((
+                ErrorKind::ClientError,
+                "the body of a cluster transaction must return one value per key",
+            ))));
+        }
+
+        let mut eval = redis::cmd("EVAL");
+        eval.arg(CAS_SCRIPT).arg(keys.len()).arg(keys);
+        for (read, written) in read.iter().zip(&written) {
+            match read {
+                Some(string) => eval.arg("v").arg(string),
+                None => eval.arg("n").arg(""),
+            };
+
+            let written = written
+                .as_ref()
+                .map(serde_json::to_string)
+                .transpose()
+                .map_err(TxError::Serialization)?;
+            match written {
+                Some(string) if read.as_ref() == Some(&string) => eval.arg("k").arg(""),
+                Some(string) => eval.arg("s").arg(string),
+                None if read.is_none() => eval.arg("k").arg(""),
+                None => eval.arg("d").arg(""),
+            };
+        }
+
+        let committed: bool = options.bounded(eval.query_async(con)).await??;
+        if committed {
+            return Ok(written);
+        }
+        options.conflicted(&mut conflicts).await?;
+    }
+}
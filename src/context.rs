@@ -0,0 +1,106 @@
+//! Errors that tell which operation on which key failed.
+//!
+//! The errors of the helpers say what went wrong but not where, a `Serialization` error in a log
+//! could have come from any read of the application. [`ErrorContext::context`] wraps the error of
+//! a call in a [`ContextError`] carrying the operation and the key, and shown with them:
+//!
+//! ```
+//! extern crate redis_utils;
+//! extern crate redis;
+//!
+//! use redis_utils::context::{ContextError, ErrorContext};
+//! use redis_utils::converters::{JsonGet, JsonGetError};
+//! # use redis_utils::mock::MockConnection;
+//!
+//! # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+//! # let mut con = MockConnection::new().with_value("user:42", "not json");
+//! let user: Result<u32, ContextError<JsonGetError>> =
+//!     con.json_get("user:42").await.context("GET", "user:42");
+//!
+//! let err = user.unwrap_err();
+//! assert!(matches!(err.source, JsonGetError::Serialization(_)));
+//! assert_eq!(
+//!     err.to_string(),
+//!     "GET user:42: invalid json: expected ident at line 1 column 2"
+//! );
+//! # });
+//! ```
+//!
+//! It works on the result of a [`tx!`](crate::tx) just the same, with the watched keys as the key:
+//!
+//! ```
+//! #[macro_use] extern crate redis_utils;
+//! extern crate redis;
+//!
+//! use redis_utils::context::{ContextError, ErrorContext};
+//! use redis_utils::TxError;
+//! # use redis_utils::mock::MockConnection;
+//!
+//! # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+//! # let mut con = MockConnection::new();
+//! let keys = ["stock", "orders"];
+//! let tx_result: Result<(), ContextError<TxError<&str>>> = tx!(&mut con, pipe, &keys, {
+//!   Err::<&mut redis::Pipeline, _>(TxError::Abort("sold out"))
+//! })
+//! .context("tx", &keys);
+//!
+//! let err = tx_result.unwrap_err();
+//! assert!(matches!(err.source, TxError::Abort("sold out")));
+//! assert_eq!(err.to_string(), "tx stock orders: transaction aborted: sold out");
+//! # });
+//! ```
+//!
+//! The context is the caller's to give: neither the helpers nor `tx!` attach it themselves, their
+//! errors keep the types they have (of which only `JsonGetError::KeyMissing` names its key). Wrap
+//! the calls whose errors end up in logs, binding the key once for both the call and its context
+//! as above.
+
+use redis::ToRedisArgs;
+use std::fmt;
+
+/// An error along with the operation and the key it happened on.
+#[derive(Debug)]
+pub struct ContextError<E> {
+    /// The operation, e.g. `GET` or `json_set`.
+    pub op: &'static str,
+    /// The arguments of the key, space separated.
+    pub key: String,
+    pub source: E,
+}
+
+impl<E: fmt::Display> fmt::Display for ContextError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}: {}", self.op, self.key, self.source)
+    }
+}
+
+impl<E: std::error::Error + 'static> std::error::Error for ContextError<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+pub trait ErrorContext<T, E> {
+    /// Wraps the error in a [`ContextError`] naming `op` and `key`.
+    fn context<Key: ToRedisArgs>(self, op: &'static str, key: Key) -> Result<T, ContextError<E>>;
+}
+
+impl<T, E> ErrorContext<T, E> for Result<T, E> {
+    fn context<Key: ToRedisArgs>(self, op: &'static str, key: Key) -> Result<T, ContextError<E>> {
+        self.map_err(|source| ContextError {
+            op,
+            key: render_key(&key),
+            source,
+        })
+    }
+}
+
+/// The arguments `key` is written as, space separated, non utf-8 bytes replaced.
+pub(crate) fn render_key<Key: ToRedisArgs>(key: &Key) -> String {
+    let args: Vec<String> = key
+        .to_redis_args()
+        .iter()
+        .map(|arg| String::from_utf8_lossy(arg).into_owned())
+        .collect();
+    args.join(" ")
+}
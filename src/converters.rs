@@ -1,9 +1,27 @@
-use crate::TxError;
+//! Extension traits that (de)serialize values with `serde_json` on their way to and from redis.
+//!
+//! Every `json_*` method stores a value as the string holding its json, so a `u8` of `42` is
+//! stored as `42` but the `String` `"hi"` is stored as `"\"hi\""`. Values written by
+//! `AsyncCommands` (or [`JsonSet::set_typed`] and [`JsonSet::set_raw_string`]) aren't json
+//! encoded, read those back with `AsyncCommands` or [`JsonRead::get_typed`]. Mixing the two on the
+//! same key works for numbers and booleans but not for strings.
+
+use crate::context::render_key;
+use crate::{TxError, TxOutcome};
 use async_trait::async_trait;
+use futures_util::stream::BoxStream;
 use redis::aio::ConnectionLike;
-use redis::{AsyncCommands, Pipeline, RedisError, ToRedisArgs};
+use redis::{AsyncCommands, FromRedisValue, Pipeline, RedisError, RedisWrite, ToRedisArgs};
 use serde::de::DeserializeOwned;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::any::Any;
+use std::collections::HashMap;
+use std::convert::{Infallible, TryFrom};
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock, PoisonError};
+use std::time::Duration;
 
 pub trait PipelineJsonSet<U> {
     fn json_set<Key: ToRedisArgs, Val: Serialize>(
@@ -11,25 +29,136 @@ pub trait PipelineJsonSet<U> {
         key: Key,
         val: Val,
     ) -> Result<&mut Self, TxError<U>>;
+    fn json_set_ref<Key: ToRedisArgs, Val: Serialize + ?Sized>(
+        &mut self,
+        key: Key,
+        val: &Val,
+    ) -> Result<&mut Self, TxError<U>>;
+    fn json_set_ex<Key: ToRedisArgs, Val: Serialize>(
+        &mut self,
+        key: Key,
+        val: Val,
+        seconds: usize,
+    ) -> Result<&mut Self, TxError<U>>;
+    fn json_del<Key: ToRedisArgs>(&mut self, key: Key) -> Result<&mut Self, TxError<U>>;
 }
 
+/// ```no_run
+/// #[macro_use] extern crate redis_utils;
+/// extern crate redis;
+///
+/// use serde::{Deserialize, Serialize};
+/// use redis::RedisResult;
+/// use redis_utils::converters::{JsonGet, PipelineJsonSet};
+/// use redis_utils::TxError;
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct Session {
+///     user: String,
+/// }
+///
+/// async fn tx_demo() -> RedisResult<()> {
+///     let mut con = redis::Client::open("redis://127.0.0.1/")?.get_async_connection().await?;
+///     let tx_result: Result<(), TxError<()>> = tx!(&mut con, pipe, &["session:old"], {
+///       let session: Session = con.json_get("session:old").await?;
+///
+///       pipe.json_set_ex("session:new", session, 3600)?.ignore();
+///       Ok(pipe.json_del("session:old")?.ignore())
+///     });
+///
+///    Ok(())
+/// }
+/// ```
 impl<U> PipelineJsonSet<U> for Pipeline {
     fn json_set<Key: ToRedisArgs, Val: Serialize>(
         &mut self,
         key: Key,
         val: Val,
+    ) -> Result<&mut Self, TxError<U>> {
+        self.json_set_ref(key, &val)
+    }
+
+    fn json_set_ref<Key: ToRedisArgs, Val: Serialize + ?Sized>(
+        &mut self,
+        key: Key,
+        val: &Val,
     ) -> Result<&mut Self, TxError<U>> {
         Ok(self.set(
+            key,
+            serde_json::to_string(val).map_err(TxError::Serialization)?,
+        ))
+    }
+
+    fn json_set_ex<Key: ToRedisArgs, Val: Serialize>(
+        &mut self,
+        key: Key,
+        val: Val,
+        seconds: usize,
+    ) -> Result<&mut Self, TxError<U>> {
+        Ok(self.set_ex(
             key,
             serde_json::to_string(&val).map_err(TxError::Serialization)?,
+            seconds,
         ))
     }
+
+    fn json_del<Key: ToRedisArgs>(&mut self, key: Key) -> Result<&mut Self, TxError<U>> {
+        Ok(self.del(key))
+    }
+}
+
+/// How a key is stored, as reported by [`JsonRead::json_debug_info`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct JsonKeyInfo {
+    /// `TYPE`: `string` for a value written by the `json_*` helpers, `none` if the key doesn't
+    /// exist.
+    pub kind: String,
+    /// `OBJECT ENCODING`, `embstr`, `raw` or `int` for a string, `None` if the key doesn't exist.
+    pub encoding: Option<String>,
+    /// The length of the value in bytes, 0 if it's not a string.
+    pub len: usize,
+    /// How long until the key expires, `None` if it doesn't exist or has no expiry.
+    pub ttl: Option<Duration>,
+    /// Whether the value parses as json, `false` if it's not a string.
+    pub valid_json: bool,
+}
+
+impl JsonKeyInfo {
+    pub fn exists(&self) -> bool {
+        self.kind != "none"
+    }
 }
 
 #[derive(Debug)]
 pub enum JsonSetError {
     Serialization(serde_json::Error),
     DbError(redis::RedisError),
+    /// The value doesn't match its json schema, with a message per violation.
+    Validation(Vec<String>),
+}
+
+impl fmt::Display for JsonSetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JsonSetError::Serialization(err) => write!(f, "could not serialize to json: {}", err),
+            JsonSetError::DbError(err) => write!(f, "{}", err),
+            JsonSetError::Validation(violations) => write!(
+                f,
+                "value doesn't match its schema: {}",
+                violations.join("; ")
+            ),
+        }
+    }
+}
+
+impl std::error::Error for JsonSetError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            JsonSetError::Serialization(err) => Some(err),
+            JsonSetError::DbError(err) => Some(err),
+            JsonSetError::Validation(_) => None,
+        }
+    }
 }
 
 impl From<RedisError> for JsonSetError {
@@ -44,6 +173,8 @@ impl From<serde_json::Error> for JsonSetError {
     }
 }
 
+/// Writes values as json strings, with `set_typed` as a raw passthrough for values that aren't
+/// json encoded (read back with [`JsonRead::get_typed`]).
 #[async_trait]
 pub trait JsonSet {
     async fn json_set<Key: ToRedisArgs + Send + Sync, Val: Serialize + Send + Sync>(
@@ -51,6 +182,55 @@ pub trait JsonSet {
         key: Key,
         val: Val,
     ) -> Result<(), JsonSetError>;
+    async fn json_set_ref<Key: ToRedisArgs + Send + Sync, Val: Serialize + ?Sized + Sync>(
+        &mut self,
+        key: Key,
+        val: &Val,
+    ) -> Result<(), JsonSetError>;
+    async fn set_typed<Key: ToRedisArgs + Send + Sync, Val: ToRedisArgs + Send + Sync>(
+        &mut self,
+        key: Key,
+        val: Val,
+    ) -> Result<(), JsonSetError>;
+    async fn json_set_content_addressed<Val: Serialize + Send + Sync>(
+        &mut self,
+        prefix: &str,
+        val: Val,
+    ) -> Result<String, JsonSetError>;
+    async fn set_raw_string<Key: ToRedisArgs + Send + Sync>(
+        &mut self,
+        key: Key,
+        val: &str,
+    ) -> Result<(), JsonSetError>;
+    async fn set_prerendered<Key: ToRedisArgs + Send + Sync, Json: ToRedisArgs + Send + Sync>(
+        &mut self,
+        key: Key,
+        json: Json,
+    ) -> Result<(), JsonSetError>;
+    async fn json_expire<Key: ToRedisArgs + Send + Sync>(
+        &mut self,
+        key: Key,
+        seconds: u64,
+    ) -> Result<bool, JsonGetError>;
+    async fn json_replace<
+        Key: ToRedisArgs + Send + Sync,
+        Val: Serialize + DeserializeOwned + Send + Sync,
+    >(
+        &mut self,
+        key: Key,
+        val: Val,
+    ) -> Result<Option<Val>, JsonSetError>;
+    async fn json_set_and_publish<Key: ToRedisArgs + Send + Sync, Val: Serialize + Send + Sync>(
+        &mut self,
+        key: Key,
+        val: Val,
+        channel: &str,
+    ) -> Result<usize, JsonSetError>;
+    async fn json_set_multi<Key: ToRedisArgs + Send + Sync, Val: Serialize + Send + Sync>(
+        &mut self,
+        keys: &[Key],
+        val: Val,
+    ) -> Result<(), JsonSetError>;
 }
 
 #[async_trait]
@@ -63,7 +243,578 @@ where
         key: Key,
         val: Val,
     ) -> Result<(), JsonSetError> {
-        Ok(self.set(key, serde_json::to_string(&val)?).await?)
+        self.json_set_ref(key, &val).await
+    }
+
+    /// serialize the borrowed value -> set it, for values the caller still needs afterwards
+    ///
+    /// ```no_run
+    /// extern crate redis_utils;
+    /// extern crate redis;
+    ///
+    /// use serde::Serialize;
+    /// use redis::RedisResult;
+    /// use redis_utils::converters::JsonSet;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Document {
+    ///     pages: Vec<String>,
+    /// }
+    ///
+    /// async fn ref_demo(document: &Document) -> RedisResult<()> {
+    ///     let mut con = redis::Client::open("redis://127.0.0.1/")?.get_async_connection().await?;
+    ///     con.json_set_ref("document", document).await.unwrap();
+    ///     con.json_set_ref("pages", &document.pages[..]).await.unwrap();
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    async fn json_set_ref<Key: ToRedisArgs + Send + Sync, Val: Serialize + ?Sized + Sync>(
+        &mut self,
+        key: Key,
+        val: &Val,
+    ) -> Result<(), JsonSetError> {
+        Ok(self.set(key, serde_json::to_string(val)?).await?)
+    }
+
+    /// serialize once -> `MSET` the same json under every key, in one round trip. Nothing is sent
+    /// for no keys
+    ///
+    /// ```
+    /// extern crate redis_utils;
+    /// extern crate redis;
+    ///
+    /// use serde::{Serialize, Serializer};
+    /// use std::sync::atomic::{AtomicUsize, Ordering};
+    /// use redis_utils::converters::JsonSet;
+    /// # use redis_utils::mock::MockConnection;
+    ///
+    /// static SERIALIZED: AtomicUsize = AtomicUsize::new(0);
+    ///
+    /// /// A value counting how many times it's serialized.
+    /// struct Config;
+    ///
+    /// impl Serialize for Config {
+    ///     fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    ///         SERIALIZED.fetch_add(1, Ordering::SeqCst);
+    ///         serializer.serialize_str("dark")
+    ///     }
+    /// }
+    ///
+    /// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+    /// # let mut con = MockConnection::new();
+    /// con.json_set_multi(&["theme:eu", "theme:us", "theme:ap"], Config).await.unwrap();
+    ///
+    /// assert_eq!(SERIALIZED.load(Ordering::SeqCst), 1);
+    /// assert_eq!(
+    ///     con.args,
+    ///     vec![vec!["MSET", "theme:eu", "\"dark\"", "theme:us", "\"dark\"", "theme:ap", "\"dark\""]]
+    /// );
+    /// # });
+    /// ```
+    async fn json_set_multi<Key: ToRedisArgs + Send + Sync, Val: Serialize + Send + Sync>(
+        &mut self,
+        keys: &[Key],
+        val: Val,
+    ) -> Result<(), JsonSetError> {
+        if keys.is_empty() {
+            return Ok(());
+        }
+
+        let json = serde_json::to_string(&val)?;
+        let mut mset = redis::cmd("MSET");
+        for key in keys {
+            mset.arg(key).arg(&json);
+        }
+        Ok(mset.query_async(self).await?)
+    }
+
+    /// set the raw value, without any json encoding
+    async fn set_typed<Key: ToRedisArgs + Send + Sync, Val: ToRedisArgs + Send + Sync>(
+        &mut self,
+        key: Key,
+        val: Val,
+    ) -> Result<(), JsonSetError> {
+        Ok(self.set(key, val).await?)
+    }
+
+    /// set the string as is, without json encoding it. `json_set("key", "hi")` stores `"hi"` (with
+    /// the quotes) and reads back with `json_get`, this stores `hi` and reads back with `get` /
+    /// `get_typed`, skipping the serialization and escaping
+    ///
+    /// ```no_run
+    /// extern crate redis_utils;
+    /// extern crate redis;
+    ///
+    /// use redis::{AsyncCommands, RedisResult};
+    /// use redis_utils::converters::JsonSet;
+    ///
+    /// async fn raw_demo() -> RedisResult<()> {
+    ///     let mut con = redis::Client::open("redis://127.0.0.1/")?.get_async_connection().await?;
+    ///     con.set_raw_string("greeting", "hi").await.unwrap();
+    ///
+    ///     let greeting: String = con.get("greeting").await?;
+    ///     assert_eq!(greeting, "hi");
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    async fn set_raw_string<Key: ToRedisArgs + Send + Sync>(
+        &mut self,
+        key: Key,
+        val: &str,
+    ) -> Result<(), JsonSetError> {
+        Ok(self.set(key, val).await?)
+    }
+
+    /// set json that's already serialized (say, held by a cache and written to several backends)
+    /// as is, skipping `serde_json`. Nothing checks that it *is* json, `json_get` will fail with a
+    /// `JsonGetError::Serialization` on whatever isn't
+    ///
+    /// ```no_run
+    /// extern crate redis_utils;
+    /// extern crate redis;
+    ///
+    /// use redis::RedisResult;
+    /// use redis_utils::converters::{JsonGet, JsonSet};
+    ///
+    /// async fn prerendered_demo(rendered: Vec<u8>) -> RedisResult<()> {
+    ///     let mut con = redis::Client::open("redis://127.0.0.1/")?.get_async_connection().await?;
+    ///     con.set_prerendered("homepage", &rendered).await.unwrap();
+    ///
+    ///     let homepage: serde_json::Value = con.json_get("homepage").await.unwrap();
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    async fn set_prerendered<Key: ToRedisArgs + Send + Sync, Json: ToRedisArgs + Send + Sync>(
+        &mut self,
+        key: Key,
+        json: Json,
+    ) -> Result<(), JsonSetError> {
+        Ok(self.set(key, json).await?)
+    }
+
+    /// expire the key in `seconds` without rewriting its value, `false` if there's no such key
+    ///
+    /// ```no_run
+    /// extern crate redis_utils;
+    /// extern crate redis;
+    ///
+    /// use std::time::Duration;
+    /// use redis::RedisResult;
+    /// use redis_utils::converters::{JsonRead, JsonSet};
+    ///
+    /// async fn ttl_demo() -> RedisResult<()> {
+    ///     let mut con = redis::Client::open("redis://127.0.0.1/")?.get_async_connection().await?;
+    ///     con.json_set("cache:page", "<html></html>").await.unwrap();
+    ///
+    ///     assert!(con.json_expire("cache:page", 60).await.unwrap());
+    ///     assert!(con.json_ttl("cache:page").await.unwrap() <= Some(Duration::from_secs(60)));
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    async fn json_expire<Key: ToRedisArgs + Send + Sync>(
+        &mut self,
+        key: Key,
+        seconds: u64,
+    ) -> Result<bool, JsonGetError> {
+        Ok(redis::cmd("EXPIRE")
+            .arg(key)
+            .arg(seconds)
+            .query_async(self)
+            .await?)
+    }
+
+    /// serialize it to json -> `SET key value XX GET` (redis 6.2 or later) -> the previous value
+    /// deserialized from json, or `None` if the key didn't exist and nothing was written. A
+    /// `JsonSetError::Serialization` either comes from encoding `val` (nothing was written) or
+    /// from decoding the previous value (`val` was written)
+    ///
+    /// ```no_run
+    /// extern crate redis_utils;
+    /// extern crate redis;
+    ///
+    /// use serde::{Deserialize, Serialize};
+    /// use redis::RedisResult;
+    /// use redis_utils::converters::JsonSet;
+    ///
+    /// #[derive(Serialize, Deserialize)]
+    /// struct Profile {
+    ///     bio: String,
+    /// }
+    ///
+    /// async fn replace_demo() -> RedisResult<()> {
+    ///     let mut con = redis::Client::open("redis://127.0.0.1/")?.get_async_connection().await?;
+    ///     let previous = con.json_replace("profile:1", Profile { bio: "hi".to_string() }).await.unwrap();
+    ///     if previous.is_none() {
+    ///         println!("no such profile, nothing written");
+    ///     }
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    async fn json_replace<
+        Key: ToRedisArgs + Send + Sync,
+        Val: Serialize + DeserializeOwned + Send + Sync,
+    >(
+        &mut self,
+        key: Key,
+        val: Val,
+    ) -> Result<Option<Val>, JsonSetError> {
+        let previous: Option<String> = redis::cmd("SET")
+            .arg(key)
+            .arg(serde_json::to_string(&val)?)
+            .arg("XX")
+            .arg("GET")
+            .query_async(self)
+            .await?;
+        Ok(from_optional_json(previous)?)
+    }
+
+    /// serialize it to json -> `SET` it and `PUBLISH` the key on `channel` in one `MULTI` / `EXEC`
+    /// -> how many subscribers received the message
+    ///
+    /// The transaction makes the two happen together: the message goes out right as the value is
+    /// written, never about a write that didn't happen. Pub/sub itself isn't transactional though,
+    /// it's fire and forget: only the subscribers connected at that moment receive the message,
+    /// nothing is kept for one that's disconnected or reconnecting, and a subscriber that starts
+    /// listening after the write never hears about it. Subscribers that can't miss a change should
+    /// read the current value when they (re)subscribe, or be fed from a stream instead (see
+    /// [`JsonStream`]). In a cluster, the count only includes the clients of the node the key
+    /// lives on.
+    ///
+    /// ```no_run
+    /// extern crate redis_utils;
+    /// extern crate redis;
+    ///
+    /// use serde::Serialize;
+    /// use redis::RedisResult;
+    /// use redis_utils::converters::JsonSet;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Price {
+    ///     cents: u64,
+    /// }
+    ///
+    /// async fn publish_demo() -> RedisResult<()> {
+    ///     let mut con = redis::Client::open("redis://127.0.0.1/")?.get_async_connection().await?;
+    ///     let notified = con
+    ///         .json_set_and_publish("price:btc", Price { cents: 4_200_000 }, "prices")
+    ///         .await
+    ///         .unwrap();
+    ///     println!("{} subscribers notified", notified);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    async fn json_set_and_publish<Key: ToRedisArgs + Send + Sync, Val: Serialize + Send + Sync>(
+        &mut self,
+        key: Key,
+        val: Val,
+        channel: &str,
+    ) -> Result<usize, JsonSetError> {
+        let (notified,): (usize,) = redis::pipe()
+            .atomic()
+            .set(&key, serde_json::to_string(&val)?)
+            .ignore()
+            .publish(channel, &key)
+            .query_async(self)
+            .await?;
+        Ok(notified)
+    }
+
+    /// canonical json -> sha-256 -> set under `prefix:<hex digest>` unless it's already there ->
+    /// the key, so equal values (whatever the order of their map keys) share a key
+    ///
+    /// ```no_run
+    /// extern crate redis_utils;
+    /// extern crate redis;
+    ///
+    /// use serde::Serialize;
+    /// use redis::RedisResult;
+    /// use redis_utils::converters::JsonSet;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Blob {
+    ///     bytes: Vec<u8>,
+    /// }
+    ///
+    /// async fn blob_demo() -> RedisResult<()> {
+    ///     let mut con = redis::Client::open("redis://127.0.0.1/")?.get_async_connection().await?;
+    ///     let key = con.json_set_content_addressed("blob", Blob { bytes: vec![1, 2, 3] }).await.unwrap();
+    ///     assert!(key.starts_with("blob:"));
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    async fn json_set_content_addressed<Val: Serialize + Send + Sync>(
+        &mut self,
+        prefix: &str,
+        val: Val,
+    ) -> Result<String, JsonSetError> {
+        let json = canonical_json(&val)?;
+        let digest: String = Sha256::digest(json.as_bytes())
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        let key = format!("{}:{}", prefix, digest);
+
+        redis::cmd("SET")
+            .arg(&key)
+            .arg(json)
+            .arg("NX")
+            .query_async::<_, ()>(self)
+            .await?;
+        Ok(key)
+    }
+}
+
+/// Reads that aren't tied to one value type, unlike [`JsonGet`]: raw, borrowing, custom decoded,
+/// or about the key rather than its value.
+#[async_trait]
+pub trait JsonRead {
+    async fn get_typed<Key: ToRedisArgs + Send + Sync, Val: FromRedisValue>(
+        &mut self,
+        key: Key,
+    ) -> Result<Val, JsonGetError>;
+    async fn json_get_into<'b, Key: ToRedisArgs + Send + Sync, Val: Deserialize<'b>>(
+        &mut self,
+        key: Key,
+        buf: &'b mut Vec<u8>,
+    ) -> Result<Val, JsonGetError>;
+    async fn json_ttl<Key: ToRedisArgs + Send + Sync>(
+        &mut self,
+        key: Key,
+    ) -> Result<Option<Duration>, JsonGetError>;
+    async fn json_debug_info<Key: ToRedisArgs + Send + Sync>(
+        &mut self,
+        key: Key,
+    ) -> Result<JsonKeyInfo, JsonGetError>;
+    async fn json_get_with<Key, Val, F>(
+        &mut self,
+        key: Key,
+        deserialize: F,
+    ) -> Result<Val, JsonGetError>
+    where
+        Key: ToRedisArgs + Send + Sync,
+        F: FnOnce(&str) -> Result<Val, serde_json::Error> + Send;
+}
+
+#[async_trait]
+impl<C> JsonRead for C
+where
+    C: ConnectionLike + Send + Sync,
+{
+    /// get -> convert the raw reply, without any json decoding
+    async fn get_typed<Key: ToRedisArgs + Send + Sync, Val: FromRedisValue>(
+        &mut self,
+        key: Key,
+    ) -> Result<Val, JsonGetError> {
+        Ok(self.get(key).await?)
+    }
+
+    /// get -> move the json of the reply into `buf`, whatever it held before, instead of a fresh
+    /// `String` -> deserialize it from there. The bytes aren't copied, the buffer takes over those
+    /// of the reply, and `Val` can borrow from the buffer (`&str` fields, say) to save allocations
+    /// of its own, which is where the gain is: an owned `Val` reads about as fast as with
+    /// `json_get`, see `benches/json_get.rs`
+    ///
+    /// ```no_run
+    /// extern crate redis_utils;
+    /// extern crate redis;
+    ///
+    /// use serde::Deserialize;
+    /// use redis::RedisResult;
+    /// use redis_utils::converters::JsonRead;
+    ///
+    /// #[derive(Deserialize)]
+    /// struct Quote<'a> {
+    ///     symbol: &'a str,
+    ///     price: f64,
+    /// }
+    ///
+    /// async fn hot_demo() -> RedisResult<()> {
+    ///     let mut con = redis::Client::open("redis://127.0.0.1/")?.get_async_connection().await?;
+    ///     let mut buf = Vec::new();
+    ///     for _ in 0..1000 {
+    ///         let quote: Quote = con.json_get_into("quote", &mut buf).await.unwrap();
+    ///         println!("{} {}", quote.symbol, quote.price);
+    ///     }
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    async fn json_get_into<'b, Key: ToRedisArgs + Send + Sync, Val: Deserialize<'b>>(
+        &mut self,
+        key: Key,
+        buf: &'b mut Vec<u8>,
+    ) -> Result<Val, JsonGetError> {
+        // the reply itself rather than a conversion of it, which would copy its bytes
+        let reply = self.req_packed_command(redis::cmd("GET").arg(&key)).await?;
+
+        *buf = match reply {
+            redis::Value::Data(bytes) => bytes,
+            redis::Value::Nil => return Err(JsonGetError::missing(&key)),
+            // anything else a `String` can be read from
+            other => String::from_redis_value(&other)?.into_bytes(),
+        };
+
+        let buf: &'b Vec<u8> = buf;
+        Ok(serde_json::from_slice(buf)?)
+    }
+
+    /// ttl -> how long until the key expires, `None` if it doesn't exist or has no expiry
+    async fn json_ttl<Key: ToRedisArgs + Send + Sync>(
+        &mut self,
+        key: Key,
+    ) -> Result<Option<Duration>, JsonGetError> {
+        let ttl: i64 = self.ttl(key).await?;
+        Ok(u64::try_from(ttl).ok().map(Duration::from_secs))
+    }
+
+    /// type, object encoding and ttl in one round trip -> get, for strings only -> how the key is
+    /// stored, see [`JsonKeyInfo`]. A missing key isn't an error, nor is a key of another type,
+    /// both show in the info. For debugging: it reads the whole value
+    ///
+    /// ```
+    /// extern crate redis_utils;
+    /// extern crate redis;
+    ///
+    /// use redis_utils::converters::JsonRead;
+    /// # use redis::Value;
+    /// # use redis_utils::mock::MockConnection;
+    /// #
+    /// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+    /// # let mut con = MockConnection::new()
+    /// #     .with_value("user:1", r#"{"name":"ada"}"#)
+    /// #     .reply("TYPE", Value::Status("string".to_string()))
+    /// #     .reply("OBJECT", Value::Data(b"embstr".to_vec()))
+    /// #     .reply("TTL", Value::Int(60))
+    /// #     .reply("TYPE", Value::Status("none".to_string()))
+    /// #     .reply("OBJECT", Value::Nil)
+    /// #     .reply("TTL", Value::Int(-2));
+    /// let info = con.json_debug_info("user:1").await.unwrap();
+    /// assert!(info.exists());
+    /// assert_eq!(info.encoding.as_deref(), Some("embstr"));
+    /// assert_eq!(info.len, 14);
+    /// assert_eq!(info.ttl, Some(std::time::Duration::from_secs(60)));
+    /// assert!(info.valid_json);
+    ///
+    /// let info = con.json_debug_info("user:2").await.unwrap();
+    /// assert!(!info.exists());
+    /// assert_eq!(info.kind, "none");
+    /// # });
+    /// ```
+    async fn json_debug_info<Key: ToRedisArgs + Send + Sync>(
+        &mut self,
+        key: Key,
+    ) -> Result<JsonKeyInfo, JsonGetError> {
+        let (kind, encoding, ttl): (String, Option<String>, i64) = redis::pipe()
+            .cmd("TYPE")
+            .arg(&key)
+            .cmd("OBJECT")
+            .arg("ENCODING")
+            .arg(&key)
+            .cmd("TTL")
+            .arg(&key)
+            .query_async(self)
+            .await?;
+
+        let mut info = JsonKeyInfo {
+            kind,
+            encoding,
+            len: 0,
+            ttl: u64::try_from(ttl).ok().map(Duration::from_secs),
+            valid_json: false,
+        };
+        if info.kind == "string" {
+            let val: Option<Vec<u8>> = self.get(&key).await?;
+            if let Some(val) = val {
+                info.len = val.len();
+                info.valid_json = serde_json::from_slice::<serde::de::IgnoredAny>(&val).is_ok();
+            }
+        }
+        Ok(info)
+    }
+
+    /// get -> decode it with `deserialize` instead of `serde_json::from_str`, for json that doesn't
+    /// quite match what `Val` expects (written by an older version, by another language). A
+    /// missing key is a `JsonGetError::KeyMissing` as with `json_get`
+    ///
+    /// ```
+    /// extern crate redis_utils;
+    /// extern crate redis;
+    ///
+    /// use serde::Deserialize;
+    /// use serde_json::Value;
+    /// use redis_utils::converters::JsonRead;
+    /// # use redis_utils::mock::MockConnection;
+    ///
+    /// #[derive(Debug, PartialEq, Deserialize)]
+    /// struct Quote {
+    ///     price: f64,
+    /// }
+    ///
+    /// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+    /// // a writer that sends prices as strings
+    /// let mut con = MockConnection::new().with_value("quote", r#"{"price":"12.5"}"#);
+    ///
+    /// let quote: Quote = con.json_get_with("quote", |json| {
+    ///     let mut quote: Value = serde_json::from_str(json)?;
+    ///     if let Some(Value::String(price)) = quote.get("price") {
+    ///         quote["price"] = price.parse::<f64>().map(Value::from).unwrap_or(Value::Null);
+    ///     }
+    ///     serde_json::from_value(quote)
+    /// }).await.unwrap();
+    ///
+    /// assert_eq!(quote, Quote { price: 12.5 });
+    /// # });
+    /// ```
+    async fn json_get_with<Key, Val, F>(
+        &mut self,
+        key: Key,
+        deserialize: F,
+    ) -> Result<Val, JsonGetError>
+    where
+        Key: ToRedisArgs + Send + Sync,
+        F: FnOnce(&str) -> Result<Val, serde_json::Error> + Send,
+    {
+        let val: Option<String> = self.get(&key).await?;
+        let val = val.ok_or_else(|| JsonGetError::missing(&key))?;
+        Ok(deserialize(&val)?)
+    }
+}
+
+/// Serializes `val` to json with the keys of every object sorted, so that logically equal values
+/// always serialize the same, e.g. for hashing or comparing them.
+///
+/// ```
+/// use std::collections::HashMap;
+/// use redis_utils::converters::canonical_json;
+///
+/// let map: HashMap<&str, u8> = vec![("b", 2), ("a", 1)].into_iter().collect();
+/// assert_eq!(canonical_json(&map).unwrap(), r#"{"a":1,"b":2}"#);
+/// ```
+pub fn canonical_json<Val: Serialize + ?Sized>(val: &Val) -> Result<String, serde_json::Error> {
+    serde_json::to_string(&sort_keys(serde_json::to_value(val)?))
+}
+
+fn sort_keys(value: Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<(String, Value)> = map.into_iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+            Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (key, sort_keys(value)))
+                    .collect(),
+            )
+        }
+        Value::Array(values) => Value::Array(values.into_iter().map(sort_keys).collect()),
+        value => value,
     }
 }
 
@@ -71,6 +822,56 @@ where
 pub enum JsonGetError {
     Serialization(serde_json::Error),
     DbError(redis::RedisError),
+    /// The key read by [`JsonGet::json_get`] (or any other read that needs a value) doesn't exist,
+    /// use the `maybe_` variant of the read when that's expected.
+    ///
+    /// ```
+    /// extern crate redis_utils;
+    /// extern crate redis;
+    ///
+    /// use redis_utils::converters::{JsonGet, JsonGetError};
+    /// # use redis_utils::mock::MockConnection;
+    /// #
+    /// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+    /// # let mut con = MockConnection::new();
+    /// let user: Result<u32, JsonGetError> = con.json_get("user:1").await;
+    /// assert!(matches!(user, Err(JsonGetError::KeyMissing { key }) if key == "user:1"));
+    ///
+    /// let user: Option<u32> = con.maybe_json_get("user:1").await.unwrap();
+    /// assert_eq!(user, None);
+    /// # });
+    /// ```
+    KeyMissing {
+        key: String,
+    },
+}
+
+impl JsonGetError {
+    pub(crate) fn missing<Key: ToRedisArgs>(key: &Key) -> Self {
+        JsonGetError::KeyMissing {
+            key: render_key(key),
+        }
+    }
+}
+
+impl fmt::Display for JsonGetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JsonGetError::Serialization(err) => write!(f, "invalid json: {}", err),
+            JsonGetError::DbError(err) => write!(f, "{}", err),
+            JsonGetError::KeyMissing { key } => write!(f, "key {} does not exist", key),
+        }
+    }
+}
+
+impl std::error::Error for JsonGetError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            JsonGetError::Serialization(err) => Some(err),
+            JsonGetError::DbError(err) => Some(err),
+            JsonGetError::KeyMissing { .. } => None,
+        }
+    }
 }
 
 impl From<RedisError> for JsonGetError {
@@ -91,10 +892,69 @@ pub trait JsonGet<Val> {
         &mut self,
         key: Key,
     ) -> Result<Val, JsonGetError>;
+    async fn json_get_arc<Key: ToRedisArgs + Send + Sync>(
+        &mut self,
+        key: Key,
+    ) -> Result<Arc<Val>, JsonGetError>;
     async fn maybe_json_get<Key: ToRedisArgs + Send + Sync>(
         &mut self,
         key: Key,
     ) -> Result<Option<Val>, JsonGetError>;
+    /// Reads the values of `key`, which may be one key or several: what decides is the number of
+    /// arguments `key` writes, not [`ToRedisArgs::is_single_arg`], which doesn't hold for every
+    /// type (it's `true` for any reference, `&Vec<String>` included, and `false` for an empty
+    /// `Vec`).
+    ///
+    /// + no argument (an empty `Vec` or slice, `None`): nothing is sent and the result is empty
+    /// + one argument (`&str`, `String`, `u64`, `&[&str; 1]`, a `Vec` or slice of one key): `GET`,
+    ///   a missing key gives an empty result
+    /// + anything more: `MGET`, with one value per key
+    ///
+    /// ```
+    /// # extern crate redis_utils;
+    /// # extern crate redis;
+    /// #
+    /// use redis_utils::converters::JsonGet;
+    /// # use redis_utils::mock::MockConnection;
+    /// #
+    /// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+    /// # let mut con = MockConnection::new()
+    /// #     .with_value("a", r#""a""#)
+    /// #     .with_value("b", r#""b""#)
+    /// #     .with_value("7", r#""7""#);
+    /// let keys = vec!["a".to_string(), "b".to_string()];
+    ///
+    /// let values: Vec<String> = con.json_mget("a").await.unwrap();
+    /// assert_eq!(values, vec!["a"]);
+    /// let values: Vec<String> = con.json_mget("a".to_string()).await.unwrap();
+    /// assert_eq!(values, vec!["a"]);
+    /// let values: Vec<String> = con.json_mget(7u64).await.unwrap();
+    /// assert_eq!(values, vec!["7"]);
+    /// let values: Vec<String> = con.json_mget(&["a"]).await.unwrap();
+    /// assert_eq!(values, vec!["a"]);
+    /// let values: Vec<String> = con.json_mget(vec!["a".to_string()]).await.unwrap();
+    /// assert_eq!(values, vec!["a"]);
+    /// let values: Vec<String> = con.json_mget(&keys[..1]).await.unwrap();
+    /// assert_eq!(values, vec!["a"]);
+    /// assert_eq!(con.sent, vec!["GET"; 6]);
+    ///
+    /// con.sent.clear();
+    /// let values: Vec<String> = con.json_mget(&keys[..]).await.unwrap();
+    /// assert_eq!(values, keys);
+    /// let values: Vec<String> = con.json_mget(&keys).await.unwrap();
+    /// assert_eq!(values, keys);
+    /// let values: Vec<String> = con.json_mget(("a", 7u64)).await.unwrap();
+    /// assert_eq!(values, vec!["a", "7"]);
+    /// assert_eq!(con.sent, vec!["MGET"; 3]);
+    ///
+    /// con.sent.clear();
+    /// let values: Vec<String> = con.json_mget(Vec::<String>::new()).await.unwrap();
+    /// assert!(values.is_empty());
+    /// let values: Vec<String> = con.json_mget(&keys[..0]).await.unwrap();
+    /// assert!(values.is_empty());
+    /// assert!(con.sent.is_empty());
+    /// # });
+    /// ```
     async fn json_mget<Key: ToRedisArgs + Send + Sync>(
         &mut self,
         key: Key,
@@ -103,8 +963,93 @@ pub trait JsonGet<Val> {
         &mut self,
         key: Key,
     ) -> Result<Vec<Val>, JsonGetError>;
+    async fn json_getex<Key: ToRedisArgs + Send + Sync>(
+        &mut self,
+        key: Key,
+        expiry: Expiry,
+    ) -> Result<Option<Val>, JsonGetError>;
+    async fn json_get_touch<Key: ToRedisArgs + Send + Sync>(
+        &mut self,
+        key: Key,
+        ttl: Duration,
+    ) -> Result<Option<Val>, JsonGetError>;
+    async fn json_get_with_ttl<Key: ToRedisArgs + Send + Sync>(
+        &mut self,
+        key: Key,
+    ) -> Result<Option<(Val, Option<Duration>)>, JsonGetError>;
+    async fn json_getdel<Key: ToRedisArgs + Send + Sync>(
+        &mut self,
+        key: Key,
+    ) -> Result<Option<Val>, JsonGetError>;
+    async fn json_get_raw<Key: ToRedisArgs + Send + Sync>(
+        &mut self,
+        key: Key,
+    ) -> Result<(Val, String), JsonGetError>;
+    async fn json_get_state<Key: ToRedisArgs + Send + Sync>(
+        &mut self,
+        key: Key,
+    ) -> Result<JsonState<Val>, JsonGetError>;
+    async fn json_mget_map(
+        &mut self,
+        keys: Vec<String>,
+    ) -> Result<HashMap<String, Val>, JsonGetError>;
+    async fn maybe_json_mget_map(
+        &mut self,
+        keys: Vec<String>,
+    ) -> Result<HashMap<String, Option<Val>>, JsonGetError>;
+}
+
+/// What [`JsonGet::json_get_state`] found under a key, telling a missing key apart from one that
+/// holds a json `null` (e.g. a cached tombstone).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum JsonState<Val> {
+    Missing,
+    Null,
+    Present(Val),
+}
+
+/// How [`JsonGet::json_getex`] updates the expiry of the key it reads.
+#[derive(Clone, Copy, Debug)]
+pub enum Expiry {
+    /// Expire in this many seconds (`EX`)
+    Seconds(u64),
+    /// Expire in this many milliseconds (`PX`)
+    Milliseconds(u64),
+    /// Expire at this unix time in seconds (`EXAT`)
+    AtSeconds(u64),
+    /// Expire at this unix time in milliseconds (`PXAT`)
+    AtMilliseconds(u64),
+    /// Remove any expiry (`PERSIST`)
+    Persist,
+}
+
+impl ToRedisArgs for Expiry {
+    fn write_redis_args<W: ?Sized + RedisWrite>(&self, out: &mut W) {
+        match *self {
+            Expiry::Seconds(secs) => ("EX", secs).write_redis_args(out),
+            Expiry::Milliseconds(millis) => ("PX", millis).write_redis_args(out),
+            Expiry::AtSeconds(secs) => ("EXAT", secs).write_redis_args(out),
+            Expiry::AtMilliseconds(millis) => ("PXAT", millis).write_redis_args(out),
+            Expiry::Persist => "PERSIST".write_redis_args(out),
+        }
+    }
+}
+
+/// Deserializes the reply of a command that returns nil for a missing key.
+#[doc(hidden)]
+pub fn from_optional_json<Val: DeserializeOwned>(
+    val: Option<String>,
+) -> Result<Option<Val>, serde_json::Error> {
+    match val {
+        Some(string) => Ok(Some(serde_json::from_str(&string)?)),
+        None => Ok(None),
+    }
 }
 
+/// Reads the value under `KEYS[1]` along with its `PTTL`, nil and -2 if the key doesn't exist.
+const GET_WITH_TTL_SCRIPT: &str =
+    "return {redis.call('GET', KEYS[1]), redis.call('PTTL', KEYS[1])}";
+
 /// ```no_run
 /// extern crate redis_utils;
 /// extern crate redis;
@@ -137,20 +1082,27 @@ where
         &mut self,
         key: Key,
     ) -> Result<Val, JsonGetError> {
-        let val: String = self.get(key).await?;
+        let val: Option<String> = self.get(&key).await?;
+        let val = val.ok_or_else(|| JsonGetError::missing(&key))?;
         Ok(serde_json::from_str(&val)?)
     }
 
+    /// get -> deserialize it from json -> wrap it in an `Arc` to share between tasks. Every call
+    /// deserializes a fresh value, see [`ArcCache`] to share one across readers
+    async fn json_get_arc<Key: ToRedisArgs + Send + Sync>(
+        &mut self,
+        key: Key,
+    ) -> Result<Arc<Val>, JsonGetError> {
+        Ok(Arc::new(self.json_get(key).await?))
+    }
+
     /// get -> deserialize it from json into an optional value
     async fn maybe_json_get<Key: ToRedisArgs + Send + Sync>(
         &mut self,
         key: Key,
     ) -> Result<Option<Val>, JsonGetError> {
         let val: Option<String> = self.get(key).await?;
-        match val {
-            Some(string) => Ok(Some(serde_json::from_str(&string)?)),
-            None => Ok(None),
-        }
+        Ok(from_optional_json(val)?)
     }
 
     /// mget -> deserialize it from json into a vector of values
@@ -158,10 +1110,17 @@ where
         &mut self,
         keys: Key,
     ) -> Result<Vec<Val>, JsonGetError> {
-        if keys.is_single_arg() {
-            Ok(self.maybe_json_get(keys).await?.into_iter().collect())
+        let mut args = keys.to_redis_args();
+        if args.is_empty() {
+            Ok(vec![])
+        } else if args.len() == 1 {
+            Ok(self
+                .maybe_json_get(args.remove(0))
+                .await?
+                .into_iter()
+                .collect())
         } else {
-            let strings: Vec<String> = self.get(keys).await?;
+            let strings: Vec<String> = redis::cmd("MGET").arg(args).query_async(self).await?;
             let mut values = vec![];
             for string in strings {
                 values.push(serde_json::from_str(&string)?)
@@ -175,7 +1134,1886 @@ where
         &mut self,
         key: Key,
     ) -> Result<Vec<Val>, JsonGetError> {
-        redis::cmd("WATCH").arg(&key).query_async(self).await?;
+        redis::cmd("WATCH")
+            .arg(&key)
+            .query_async::<_, ()>(self)
+            .await?;
         Ok(self.json_mget(key).await?)
     }
+
+    /// getex -> deserialize it from json into an optional value
+    async fn json_getex<Key: ToRedisArgs + Send + Sync>(
+        &mut self,
+        key: Key,
+        expiry: Expiry,
+    ) -> Result<Option<Val>, JsonGetError> {
+        let val: Option<String> = redis::cmd("GETEX")
+            .arg(key)
+            .arg(expiry)
+            .query_async(self)
+            .await?;
+        Ok(from_optional_json(val)?)
+    }
+
+    /// getex with `ttl` as the new expiry -> deserialize it from json into an optional value, in
+    /// one command (redis 6.2 or later): a sliding expiry for session-like values that live for
+    /// as long as they keep being read. `None` if the key is gone, expired included, nothing is
+    /// created then. `ttl` is rounded down to milliseconds, and has to be at least one
+    ///
+    /// ```no_run
+    /// extern crate redis_utils;
+    /// extern crate redis;
+    ///
+    /// use std::time::Duration;
+    /// use serde::Deserialize;
+    /// use redis::RedisResult;
+    /// use redis_utils::converters::JsonGet;
+    ///
+    /// #[derive(Deserialize)]
+    /// struct Session {
+    ///     user: String,
+    /// }
+    ///
+    /// async fn session_demo() -> RedisResult<()> {
+    ///     let mut con = redis::Client::open("redis://127.0.0.1/")?.get_async_connection().await?;
+    ///     let idle = Duration::from_secs(30 * 60);
+    ///     match con.json_get_touch("session:abc", idle).await.unwrap() {
+    ///         Some(Session { user }) => println!("{} is still logged in", user),
+    ///         None => println!("logged out after {:?} idle", idle),
+    ///     }
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    async fn json_get_touch<Key: ToRedisArgs + Send + Sync>(
+        &mut self,
+        key: Key,
+        ttl: Duration,
+    ) -> Result<Option<Val>, JsonGetError> {
+        let millis = u64::try_from(ttl.as_millis()).unwrap_or(u64::MAX);
+        self.json_getex(key, Expiry::Milliseconds(millis)).await
+    }
+
+    /// get and pttl in one script -> deserialize it from json along with how long it has left, to
+    /// the millisecond, `None` for a key with no expiry. `None` altogether if the key doesn't exist
+    ///
+    /// The script reads both at once, so the ttl is always the one of the value read. Unlike a
+    /// `MULTI` / `EXEC`, whose `EXEC` would drop the watches of the connection, it's safe to call
+    /// from the body of a [`tx!`](crate::tx): the transaction stays guarded by its `WATCH`. It's
+    /// sent with `EVAL` each time, as the `script` feature of redis-rs (`EVALSHA`) isn't enabled.
+    ///
+    /// ```
+    /// extern crate redis_utils;
+    /// extern crate redis;
+    ///
+    /// use std::time::Duration;
+    /// use redis_utils::converters::JsonGet;
+    /// # use redis::Value;
+    /// # use redis_utils::mock::MockConnection;
+    /// #
+    /// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+    /// # let read = |val: Option<&str>, ttl| {
+    /// #     let val = val.map_or(Value::Nil, |val| Value::Data(val.into()));
+    /// #     Value::Bulk(vec![val, Value::Int(ttl)])
+    /// # };
+    /// # let mut con = MockConnection::new()
+    /// #     .reply("EVAL", read(Some("7"), 1500))
+    /// #     .reply("EVAL", read(Some("3"), -1))
+    /// #     .reply("EVAL", read(None, -2));
+    /// let hot: Option<(u32, Option<Duration>)> = con.json_get_with_ttl("cache:hot").await.unwrap();
+    /// assert_eq!(hot, Some((7, Some(Duration::from_millis(1500)))));
+    ///
+    /// let cold: Option<(u32, Option<Duration>)> = con.json_get_with_ttl("cache:cold").await.unwrap();
+    /// assert_eq!(cold, Some((3, None)));
+    ///
+    /// let missing: Option<(u32, Option<Duration>)> = con.json_get_with_ttl("cache:gone").await.unwrap();
+    /// assert_eq!(missing, None);
+    /// # assert_eq!(con.sent, vec!["EVAL", "EVAL", "EVAL"], "the reads aren't in a MULTI / EXEC");
+    /// # });
+    /// ```
+    async fn json_get_with_ttl<Key: ToRedisArgs + Send + Sync>(
+        &mut self,
+        key: Key,
+    ) -> Result<Option<(Val, Option<Duration>)>, JsonGetError> {
+        let (val, ttl): (Option<String>, i64) = redis::cmd("EVAL")
+            .arg(GET_WITH_TTL_SCRIPT)
+            .arg(1)
+            .arg(&key)
+            .query_async(self)
+            .await?;
+        let ttl = u64::try_from(ttl).ok().map(Duration::from_millis);
+        Ok(from_optional_json(val)?.map(|val| (val, ttl)))
+    }
+
+    /// getdel -> deserialize it from json into an optional value
+    async fn json_getdel<Key: ToRedisArgs + Send + Sync>(
+        &mut self,
+        key: Key,
+    ) -> Result<Option<Val>, JsonGetError> {
+        let val: Option<String> = redis::cmd("GETDEL").arg(key).query_async(self).await?;
+        Ok(from_optional_json(val)?)
+    }
+
+    /// get -> deserialize it from json, keeping the json it was stored as
+    async fn json_get_raw<Key: ToRedisArgs + Send + Sync>(
+        &mut self,
+        key: Key,
+    ) -> Result<(Val, String), JsonGetError> {
+        let raw: Option<String> = self.get(&key).await?;
+        let raw = raw.ok_or_else(|| JsonGetError::missing(&key))?;
+        let val = serde_json::from_str(&raw)?;
+        Ok((val, raw))
+    }
+
+    /// get -> tell a missing key and a json `null` apart -> deserialize anything else from json
+    async fn json_get_state<Key: ToRedisArgs + Send + Sync>(
+        &mut self,
+        key: Key,
+    ) -> Result<JsonState<Val>, JsonGetError> {
+        let val: Option<String> = self.get(key).await?;
+        match val {
+            None => Ok(JsonState::Missing),
+            Some(string) if string.trim() == "null" => Ok(JsonState::Null),
+            Some(string) => Ok(JsonState::Present(serde_json::from_str(&string)?)),
+        }
+    }
+
+    /// mget -> deserialize each value from json, keyed by its key, leaving out missing keys
+    ///
+    /// ```no_run
+    /// extern crate redis_utils;
+    /// extern crate redis;
+    ///
+    /// use std::collections::HashMap;
+    /// use serde::Deserialize;
+    /// use redis::RedisResult;
+    /// use redis_utils::converters::JsonGet;
+    ///
+    /// #[derive(Deserialize)]
+    /// struct Price {
+    ///     cents: u64,
+    /// }
+    ///
+    /// async fn lookup_demo() -> RedisResult<()> {
+    ///     let mut con = redis::Client::open("redis://127.0.0.1/")?.get_async_connection().await?;
+    ///     let keys = vec!["price:apple".to_string(), "price:pear".to_string()];
+    ///     let prices: HashMap<String, Price> = con.json_mget_map(keys).await.unwrap();
+    ///
+    ///     if let Some(apple) = prices.get("price:apple") {
+    ///         println!("apples cost {} cents", apple.cents);
+    ///     }
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    async fn json_mget_map(
+        &mut self,
+        keys: Vec<String>,
+    ) -> Result<HashMap<String, Val>, JsonGetError> {
+        let values = self.maybe_json_mget_map(keys).await?;
+        Ok(values
+            .into_iter()
+            .filter_map(|(key, val)| val.map(|val| (key, val)))
+            .collect())
+    }
+
+    /// mget -> deserialize each value from json, keyed by its key, with `None` for missing keys
+    async fn maybe_json_mget_map(
+        &mut self,
+        keys: Vec<String>,
+    ) -> Result<HashMap<String, Option<Val>>, JsonGetError> {
+        if keys.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let strings: Vec<Option<String>> = redis::cmd("MGET").arg(&keys).query_async(self).await?;
+        let mut values = HashMap::with_capacity(keys.len());
+        for (key, string) in keys.into_iter().zip(strings) {
+            values.insert(key, from_optional_json(string)?);
+        }
+        Ok(values)
+    }
+}
+
+/// Gets several keys that hold different types with a single `MGET`, deserializing each one from
+/// json into its own type. Missing keys come back as `None` in their position.
+///
+/// ```no_run
+/// #[macro_use] extern crate redis_utils;
+/// extern crate redis;
+///
+/// use serde::Deserialize;
+/// use redis::RedisResult;
+/// use redis_utils::converters::JsonGetError;
+///
+/// #[derive(Deserialize)]
+/// struct Person {
+///     name: String,
+/// }
+///
+/// #[derive(Deserialize)]
+/// struct Settings {
+///     dark_mode: bool,
+/// }
+///
+/// async fn tuple_demo() -> RedisResult<()> {
+///     let mut con = redis::Client::open("redis://127.0.0.1/")?.get_async_connection().await?;
+///     let fetched: Result<(Option<Person>, Option<Settings>), JsonGetError> =
+///         json_mget_tuple!(&mut con, ("person:1" => Person, "settings:1" => Settings));
+///
+///     Ok(())
+/// }
+/// ```
+#[macro_export]
+macro_rules! json_mget_tuple {
+    ($conn:expr, ($($key:expr => $val:ty),+ $(,)?)) => {
+        match $crate::redis::cmd("MGET")
+            $(.arg($key))+
+            .query_async::<_, ::std::vec::Vec<::core::option::Option<::std::string::String>>>($conn)
+            .await
+        {
+            ::core::result::Result::Err(err) => ::core::result::Result::Err(
+                $crate::converters::JsonGetError::from(err),
+            ),
+            ::core::result::Result::Ok(strings) => {
+                let mut strings = strings.into_iter();
+                (|| -> ::core::result::Result<_, $crate::converters::JsonGetError> {
+                    ::core::result::Result::Ok(($(
+                        $crate::converters::from_optional_json::<$val>(
+                            strings.next().flatten(),
+                        )?,
+                    )+))
+                })()
+            }
+        }
+    };
+}
+
+/// [`JsonGet::json_get`] on `reader`, a separate connection reads are routed to (say, to a
+/// replica) while writes go through another one.
+///
+/// Replicas lag behind their primary, so a value read right after writing it may be stale; keep
+/// reads that a transaction depends on on the connection it watches with. Cluster replicas only
+/// serve reads once `READONLY` was sent on their connection.
+///
+/// ```no_run
+/// extern crate redis_utils;
+/// extern crate redis;
+///
+/// use serde::{Deserialize, Serialize};
+/// use redis::RedisResult;
+/// use redis_utils::converters::{json_get_from, JsonSet};
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct Article {
+///     title: String,
+/// }
+///
+/// async fn split_demo() -> RedisResult<()> {
+///     let mut primary = redis::Client::open("redis://primary/")?.get_async_connection().await?;
+///     let mut replica = redis::Client::open("redis://replica/")?.get_async_connection().await?;
+///
+///     primary.json_set("article:1", Article { title: "Hello".to_string() }).await.unwrap();
+///     let article: Article = json_get_from(&mut replica, "article:1").await.unwrap();
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn json_get_from<Reader, Key, Val>(
+    reader: &mut Reader,
+    key: Key,
+) -> Result<Val, JsonGetError>
+where
+    Reader: ConnectionLike + Send + Sync,
+    Key: ToRedisArgs + Send + Sync,
+    Val: DeserializeOwned,
+{
+    reader.json_get(key).await
+}
+
+/// [`JsonGet::maybe_json_get`] on `reader`, see [`json_get_from`].
+pub async fn maybe_json_get_from<Reader, Key, Val>(
+    reader: &mut Reader,
+    key: Key,
+) -> Result<Option<Val>, JsonGetError>
+where
+    Reader: ConnectionLike + Send + Sync,
+    Key: ToRedisArgs + Send + Sync,
+    Val: DeserializeOwned,
+{
+    reader.maybe_json_get(key).await
+}
+
+/// Shares the values read with [`ArcCache::json_get_arc`] between every reader of a key, a
+/// process-local cache for values read often and changed rarely, like configuration.
+///
+/// Each read is still a `GET`, so it always sees the latest value, but the value is only
+/// deserialized when its json differs from the one cached for the key (compared by their SHA-256
+/// digest); otherwise readers get a clone of the same `Arc`. A changed value replaces the cached
+/// one on its next read. Clones of an `ArcCache` share their entries. To save the round trip as
+/// well, see `CachedJsonGet::json_get_arc` (the `client-cache` feature).
+///
+/// ```no_run
+/// extern crate redis_utils;
+/// extern crate redis;
+///
+/// use std::sync::Arc;
+/// use serde::Deserialize;
+/// use redis::RedisResult;
+/// use redis_utils::converters::ArcCache;
+///
+/// #[derive(Deserialize)]
+/// struct Config {
+///     features: Vec<String>,
+/// }
+///
+/// async fn config_demo(cache: ArcCache) -> RedisResult<()> {
+///     let mut con = redis::Client::open("redis://127.0.0.1/")?.get_async_connection().await?;
+///     let first: Arc<Config> = cache.json_get_arc(&mut con, "config").await.unwrap();
+///     let second: Arc<Config> = cache.json_get_arc(&mut con, "config").await.unwrap();
+///     // deserialized once, as long as `config` didn't change in between
+///     assert!(Arc::ptr_eq(&first, &second));
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Clone, Default)]
+pub struct ArcCache {
+    entries: Arc<Mutex<HashMap<String, ArcEntry>>>,
+}
+
+struct ArcEntry {
+    digest: Vec<u8>,
+    val: Arc<dyn Any + Send + Sync>,
+}
+
+impl ArcCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// [`JsonGet::json_get_arc`], sharing the value with the other readers of `key` while its json
+    /// stays the same.
+    pub async fn json_get_arc<C, Val>(
+        &self,
+        con: &mut C,
+        key: &str,
+    ) -> Result<Arc<Val>, JsonGetError>
+    where
+        C: ConnectionLike + Send,
+        Val: DeserializeOwned + Send + Sync + 'static,
+    {
+        let json: Option<String> = redis::cmd("GET").arg(key).query_async(con).await?;
+        let json = json.ok_or_else(|| JsonGetError::missing(&key))?;
+        let digest = Sha256::digest(json.as_bytes()).to_vec();
+
+        let cached = self.entries().get(key).and_then(|entry| {
+            let unchanged = entry.digest == digest;
+            unchanged
+                .then(|| Arc::clone(&entry.val).downcast::<Val>().ok())
+                .flatten()
+        });
+        if let Some(val) = cached {
+            return Ok(val);
+        }
+
+        let val: Arc<Val> = Arc::new(serde_json::from_str(&json)?);
+        let entry = ArcEntry {
+            digest,
+            val: Arc::clone(&val) as Arc<dyn Any + Send + Sync>,
+        };
+        self.entries().insert(key.to_owned(), entry);
+        Ok(val)
+    }
+
+    /// Drops the value cached for `key`.
+    pub fn remove(&self, key: &str) {
+        self.entries().remove(key);
+    }
+
+    /// Drops every cached value.
+    pub fn clear(&self) {
+        self.entries().clear();
+    }
+
+    fn entries(&self) -> MutexGuard<'_, HashMap<String, ArcEntry>> {
+        self.entries.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// [`JsonGet::json_mget`] on `reader`, see [`json_get_from`].
+pub async fn json_mget_from<Reader, Key, Val>(
+    reader: &mut Reader,
+    keys: Key,
+) -> Result<Vec<Val>, JsonGetError>
+where
+    Reader: ConnectionLike + Send + Sync,
+    Key: ToRedisArgs + Send + Sync,
+    Val: DeserializeOwned,
+{
+    reader.json_mget(keys).await
+}
+
+#[async_trait]
+pub trait JsonValue {
+    async fn json_get_value<Key: ToRedisArgs + Send + Sync>(
+        &mut self,
+        key: Key,
+    ) -> Result<serde_json::Value, JsonGetError>;
+    async fn json_set_value<Key: ToRedisArgs + Send + Sync>(
+        &mut self,
+        key: Key,
+        val: &serde_json::Value,
+    ) -> Result<(), JsonSetError>;
+}
+
+/// ```no_run
+/// extern crate redis_utils;
+/// extern crate redis;
+///
+/// use redis::RedisResult;
+/// use redis_utils::converters::JsonValue;
+///
+/// async fn migrate_demo() -> RedisResult<()> {
+///     let mut con = redis::Client::open("redis://127.0.0.1/")?.get_async_connection().await?;
+///     let mut doc = con.json_get_value("key").await.unwrap();
+///     doc["migrated"] = serde_json::Value::Bool(true);
+///     con.json_set_value("key", &doc).await.unwrap();
+///
+///     Ok(())
+/// }
+/// ```
+#[async_trait]
+impl<C> JsonValue for C
+where
+    C: ConnectionLike + Send + Sync,
+{
+    /// get -> parse it into an untyped json value
+    async fn json_get_value<Key: ToRedisArgs + Send + Sync>(
+        &mut self,
+        key: Key,
+    ) -> Result<serde_json::Value, JsonGetError> {
+        self.json_get(key).await
+    }
+
+    /// serialize an untyped json value -> set
+    async fn json_set_value<Key: ToRedisArgs + Send + Sync>(
+        &mut self,
+        key: Key,
+        val: &serde_json::Value,
+    ) -> Result<(), JsonSetError> {
+        self.json_set(key, val).await
+    }
+}
+
+#[async_trait]
+pub trait JsonList {
+    async fn json_rpush<Key: ToRedisArgs + Send + Sync, Val: Serialize + Send + Sync>(
+        &mut self,
+        key: Key,
+        val: Val,
+    ) -> Result<(), JsonSetError>;
+    async fn json_lpush<Key: ToRedisArgs + Send + Sync, Val: Serialize + Send + Sync>(
+        &mut self,
+        key: Key,
+        val: Val,
+    ) -> Result<(), JsonSetError>;
+    async fn json_lrange<Key: ToRedisArgs + Send + Sync, Val: DeserializeOwned>(
+        &mut self,
+        key: Key,
+        start: isize,
+        stop: isize,
+    ) -> Result<Vec<Val>, JsonGetError>;
+    async fn json_lpop<Key: ToRedisArgs + Send + Sync, Val: DeserializeOwned>(
+        &mut self,
+        key: Key,
+    ) -> Result<Option<Val>, JsonGetError>;
+    async fn json_blpop<Key: ToRedisArgs + Send + Sync, Val: DeserializeOwned>(
+        &mut self,
+        key: Key,
+        timeout: Duration,
+    ) -> Result<Option<Val>, JsonGetError>;
+    fn json_stream_queue<'a, Key, Val>(
+        &'a mut self,
+        key: Key,
+    ) -> BoxStream<'a, Result<Val, JsonGetError>>
+    where
+        Key: ToRedisArgs + Send + Sync + 'a,
+        Val: DeserializeOwned + Send + 'a;
+}
+
+/// ```no_run
+/// extern crate redis_utils;
+/// extern crate redis;
+///
+/// use serde::{Deserialize, Serialize};
+/// use redis::RedisResult;
+/// use redis_utils::converters::JsonList;
+///
+/// #[derive(Serialize, Deserialize, PartialEq, Debug)]
+/// struct Job {
+///     id: u32,
+/// }
+///
+/// async fn list_demo() -> RedisResult<()> {
+///     let mut con = redis::Client::open("redis://127.0.0.1/")?.get_async_connection().await?;
+///     con.json_rpush("jobs", Job { id: 1 }).await.unwrap();
+///     con.json_rpush("jobs", Job { id: 2 }).await.unwrap();
+///
+///     let jobs: Vec<Job> = con.json_lrange("jobs", 0, -1).await.unwrap();
+///     assert_eq!(jobs, vec![Job { id: 1 }, Job { id: 2 }]);
+///
+///     let first: Option<Job> = con.json_lpop("jobs").await.unwrap();
+///     assert_eq!(first, Some(Job { id: 1 }));
+///
+///     Ok(())
+/// }
+/// ```
+#[async_trait]
+impl<C> JsonList for C
+where
+    C: ConnectionLike + Send + Sync,
+{
+    /// serialize it to json -> rpush
+    async fn json_rpush<Key: ToRedisArgs + Send + Sync, Val: Serialize + Send + Sync>(
+        &mut self,
+        key: Key,
+        val: Val,
+    ) -> Result<(), JsonSetError> {
+        Ok(self.rpush(key, serde_json::to_string(&val)?).await?)
+    }
+
+    /// serialize it to json -> lpush
+    async fn json_lpush<Key: ToRedisArgs + Send + Sync, Val: Serialize + Send + Sync>(
+        &mut self,
+        key: Key,
+        val: Val,
+    ) -> Result<(), JsonSetError> {
+        Ok(self.lpush(key, serde_json::to_string(&val)?).await?)
+    }
+
+    /// lrange -> deserialize each member from json
+    async fn json_lrange<Key: ToRedisArgs + Send + Sync, Val: DeserializeOwned>(
+        &mut self,
+        key: Key,
+        start: isize,
+        stop: isize,
+    ) -> Result<Vec<Val>, JsonGetError> {
+        let strings: Vec<String> = self.lrange(key, start, stop).await?;
+        let mut values = vec![];
+        for string in strings {
+            values.push(serde_json::from_str(&string)?)
+        }
+        Ok(values)
+    }
+
+    /// lpop -> deserialize it from json into an optional value
+    async fn json_lpop<Key: ToRedisArgs + Send + Sync, Val: DeserializeOwned>(
+        &mut self,
+        key: Key,
+    ) -> Result<Option<Val>, JsonGetError> {
+        let val: Option<String> = self.lpop(key, None).await?;
+        Ok(from_optional_json(val)?)
+    }
+
+    /// blpop -> deserialize the popped element from json, `None` if nothing came within `timeout`.
+    /// A zero `timeout` blocks until something comes, fractions of a second need redis 6 or later.
+    ///
+    /// The connection is blocked along with the pop: on a `MultiplexedConnection` (or a clone of
+    /// one, a `ConnectionManager`) every other command sent through it waits behind the pop, so
+    /// give consumers a connection of their own. Dropping the pop while it blocks doesn't cancel
+    /// it on the server: an element popped afterwards is lost, and its reply is left unread on a
+    /// plain `aio::Connection` (see [`guard`](crate::guard)), bound the pop with `timeout` rather
+    /// than with a `tokio::time::timeout`
+    async fn json_blpop<Key: ToRedisArgs + Send + Sync, Val: DeserializeOwned>(
+        &mut self,
+        key: Key,
+        timeout: Duration,
+    ) -> Result<Option<Val>, JsonGetError> {
+        let popped: Option<(String, String)> = redis::cmd("BLPOP")
+            .arg(key)
+            .arg(timeout.as_secs_f64())
+            .query_async(self)
+            .await?;
+        Ok(from_optional_json(popped.map(|(_, val)| val))?)
+    }
+
+    /// json_blpop, over and over: a stream of the values pushed onto the list, in order, for
+    /// consuming it as a queue. It has the connection to itself for as long as it lives, which is
+    /// what a blocking pop needs anyway, see `json_blpop`.
+    ///
+    /// A value that doesn't deserialize comes out as an error and the stream goes on with the next
+    /// one, it's popped all the same. Any other error ends the stream, the connection is most
+    /// likely broken
+    ///
+    /// ```
+    /// extern crate redis_utils;
+    /// extern crate redis;
+    ///
+    /// use futures_util::StreamExt;
+    /// use serde::Deserialize;
+    /// use redis_utils::converters::{JsonGetError, JsonList};
+    /// # use redis::{RedisError, Value};
+    /// # use redis_utils::mock::MockConnection;
+    /// # fn popped(json: &str) -> Value {
+    /// #     Value::Bulk(vec![Value::Data(b"jobs".to_vec()), Value::Data(json.as_bytes().to_vec())])
+    /// # }
+    ///
+    /// #[derive(Debug, PartialEq, Deserialize)]
+    /// struct Job {
+    ///     id: u32,
+    /// }
+    ///
+    /// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+    /// # let mut con = MockConnection::new()
+    /// #     .reply("BLPOP", popped(r#"{"id":1}"#))
+    /// #     .reply("BLPOP", popped("oops"))
+    /// #     .reply("BLPOP", popped(r#"{"id":2}"#))
+    /// #     .fail("BLPOP", RedisError::from(std::io::Error::from(std::io::ErrorKind::ConnectionReset)));
+    /// let mut jobs = con.json_stream_queue::<_, Job>("jobs");
+    ///
+    /// assert_eq!(jobs.next().await.unwrap().ok(), Some(Job { id: 1 }));
+    /// assert!(matches!(jobs.next().await, Some(Err(JsonGetError::Serialization(_)))));
+    /// assert_eq!(jobs.next().await.unwrap().ok(), Some(Job { id: 2 }));
+    /// assert!(matches!(jobs.next().await, Some(Err(JsonGetError::DbError(_)))));
+    /// assert!(jobs.next().await.is_none());
+    /// # });
+    /// ```
+    fn json_stream_queue<'a, Key, Val>(
+        &'a mut self,
+        key: Key,
+    ) -> BoxStream<'a, Result<Val, JsonGetError>>
+    where
+        Key: ToRedisArgs + Send + Sync + 'a,
+        Val: DeserializeOwned + Send + 'a,
+    {
+        let queue = futures_util::stream::unfold(Some((self, key)), |consumer| async move {
+            let (con, key) = consumer?;
+            loop {
+                match JsonList::json_blpop(&mut *con, &key, Duration::ZERO).await {
+                    Ok(Some(val)) => return Some((Ok(val), Some((con, key)))),
+                    Ok(None) => continue,
+                    Err(err @ JsonGetError::Serialization(_)) => {
+                        return Some((Err(err), Some((con, key))))
+                    }
+                    Err(err) => return Some((Err(err), None)),
+                }
+            }
+        });
+        Box::pin(queue)
+    }
+}
+
+/// Sets of json members. Set operations compare members byte for byte, so members are written with
+/// [`canonical_json`] to make logically equal values (say maps whose keys were inserted in a
+/// different order) the same member. Members written any other way, with `AsyncCommands::sadd` for
+/// instance, only match if they happen to serialize the same.
+#[async_trait]
+pub trait JsonCollection {
+    async fn json_sadd<Key: ToRedisArgs + Send + Sync, Val: Serialize + Send + Sync>(
+        &mut self,
+        key: Key,
+        val: Val,
+    ) -> Result<bool, JsonSetError>;
+    async fn json_sinter<Key: ToRedisArgs + Send + Sync, Val: DeserializeOwned>(
+        &mut self,
+        keys: Key,
+    ) -> Result<Vec<Val>, JsonGetError>;
+    async fn json_sunion<Key: ToRedisArgs + Send + Sync, Val: DeserializeOwned>(
+        &mut self,
+        keys: Key,
+    ) -> Result<Vec<Val>, JsonGetError>;
+    async fn json_sdiff<Key: ToRedisArgs + Send + Sync, Val: DeserializeOwned>(
+        &mut self,
+        keys: Key,
+    ) -> Result<Vec<Val>, JsonGetError>;
+}
+
+/// ```no_run
+/// extern crate redis_utils;
+/// extern crate redis;
+///
+/// use serde::{Deserialize, Serialize};
+/// use redis::RedisResult;
+/// use redis_utils::converters::JsonCollection;
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct Tag {
+///     name: String,
+/// }
+///
+/// async fn set_demo() -> RedisResult<()> {
+///     let mut con = redis::Client::open("redis://127.0.0.1/")?.get_async_connection().await?;
+///     con.json_sadd("post:1:tags", Tag { name: "rust".to_string() }).await.unwrap();
+///     con.json_sadd("post:2:tags", Tag { name: "rust".to_string() }).await.unwrap();
+///
+///     let shared: Vec<Tag> = con.json_sinter(&["post:1:tags", "post:2:tags"]).await.unwrap();
+///     assert_eq!(shared.len(), 1);
+///
+///     Ok(())
+/// }
+/// ```
+#[async_trait]
+impl<C> JsonCollection for C
+where
+    C: ConnectionLike + Send + Sync,
+{
+    /// serialize it to canonical json -> sadd, `true` if it wasn't a member yet
+    async fn json_sadd<Key: ToRedisArgs + Send + Sync, Val: Serialize + Send + Sync>(
+        &mut self,
+        key: Key,
+        val: Val,
+    ) -> Result<bool, JsonSetError> {
+        Ok(self.sadd(key, canonical_json(&val)?).await?)
+    }
+
+    /// sinter -> deserialize each member from json
+    async fn json_sinter<Key: ToRedisArgs + Send + Sync, Val: DeserializeOwned>(
+        &mut self,
+        keys: Key,
+    ) -> Result<Vec<Val>, JsonGetError> {
+        from_json_members(self.sinter(keys).await?)
+    }
+
+    /// sunion -> deserialize each member from json
+    async fn json_sunion<Key: ToRedisArgs + Send + Sync, Val: DeserializeOwned>(
+        &mut self,
+        keys: Key,
+    ) -> Result<Vec<Val>, JsonGetError> {
+        from_json_members(self.sunion(keys).await?)
+    }
+
+    /// sdiff -> deserialize each member from json
+    async fn json_sdiff<Key: ToRedisArgs + Send + Sync, Val: DeserializeOwned>(
+        &mut self,
+        keys: Key,
+    ) -> Result<Vec<Val>, JsonGetError> {
+        from_json_members(self.sdiff(keys).await?)
+    }
+}
+
+fn from_json_members<Val: DeserializeOwned>(
+    members: Vec<String>,
+) -> Result<Vec<Val>, JsonGetError> {
+    let mut values = vec![];
+    for member in members {
+        values.push(serde_json::from_str(&member)?)
+    }
+    Ok(values)
+}
+
+/// Structs stored as hashes, one field per struct field, rather than as one json string. A field
+/// holds its value as is, the way `HSET` writes it (`36`, `true`, `ada`), and only non scalar
+/// values (a `Vec`, a nested struct, an enum with data) are json.
+#[async_trait]
+pub trait HashStruct {
+    async fn hget_struct<Key: ToRedisArgs + Send + Sync, Val: DeserializeOwned>(
+        &mut self,
+        key: Key,
+    ) -> Result<Option<Val>, JsonGetError>;
+}
+
+/// ```
+/// extern crate redis_utils;
+/// extern crate redis;
+///
+/// use serde::Deserialize;
+/// use redis::Value;
+/// use redis_utils::converters::HashStruct;
+/// # use redis_utils::mock::MockConnection;
+///
+/// #[derive(Debug, PartialEq, Deserialize)]
+/// #[serde(rename_all = "lowercase")]
+/// enum Role {
+///     Admin,
+///     Member,
+/// }
+///
+/// #[derive(Debug, PartialEq, Deserialize)]
+/// struct User {
+///     name: String,
+///     age: u8,
+///     verified: bool,
+///     role: Role,
+///     tags: Vec<String>,
+///     nickname: Option<String>,
+/// }
+///
+/// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+/// # let fields = vec![("name", "ada"), ("age", "36"), ("verified", "1"), ("role", "admin"), ("tags", r#"["math"]"#)];
+/// # let hash = Value::Bulk(fields.iter().flat_map(|(field, val)| vec![*field, *val]).map(|arg| Value::Data(arg.into())).collect());
+/// # let mut con = MockConnection::new().reply("HGETALL", hash).reply("HGETALL", Value::Bulk(vec![]));
+/// let user: Option<User> = con.hget_struct("user:1").await.unwrap();
+/// assert_eq!(user, Some(User {
+///     name: "ada".to_string(),
+///     age: 36,
+///     verified: true,
+///     role: Role::Admin,
+///     tags: vec!["math".to_string()],
+///     nickname: None,
+/// }));
+///
+/// let missing: Option<User> = con.hget_struct("user:2").await.unwrap();
+/// assert_eq!(missing, None);
+/// # });
+/// ```
+#[async_trait]
+impl<C> HashStruct for C
+where
+    C: ConnectionLike + Send + Sync,
+{
+    /// hgetall -> build `Val` from its fields, `None` if the hash is empty or missing. Numbers and
+    /// booleans (`true`/`false` or `1`/`0`) are parsed from their field, sequences, maps and
+    /// structs are parsed as json, and a field that isn't there counts as missing, so `Option`s
+    /// and `#[serde(default)]` work across fields added later
+    async fn hget_struct<Key: ToRedisArgs + Send + Sync, Val: DeserializeOwned>(
+        &mut self,
+        key: Key,
+    ) -> Result<Option<Val>, JsonGetError> {
+        let fields: HashMap<String, String> = self.hgetall(key).await?;
+        if fields.is_empty() {
+            return Ok(None);
+        }
+
+        let fields = fields
+            .iter()
+            .map(|(field, val)| (field.as_str(), HashField(val)));
+        let deserializer = serde::de::value::MapDeserializer::<_, serde_json::Error>::new(fields);
+        Ok(Some(Val::deserialize(deserializer)?))
+    }
+}
+
+/// The value of a hash field, deserialized as [`HashStruct`] describes.
+struct HashField<'de>(&'de str);
+
+impl<'de> HashField<'de> {
+    fn parse<T: std::str::FromStr>(&self, expected: &str) -> Result<T, serde_json::Error>
+    where
+        T::Err: std::fmt::Display,
+    {
+        use serde::de::Error as _;
+
+        self.0.parse().map_err(|err| {
+            serde_json::Error::custom(format!("{:?} is not {}: {}", self.0, expected, err))
+        })
+    }
+}
+
+impl<'de> serde::de::IntoDeserializer<'de, serde_json::Error> for HashField<'de> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+macro_rules! parse_field {
+    ($($deserialize:ident => $visit:ident: $ty:ty),* $(,)?) => {
+        $(
+            fn $deserialize<V: serde::de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+                visitor.$visit(self.parse::<$ty>(stringify!($ty))?)
+            }
+        )*
+    };
+}
+
+impl<'de> serde::Deserializer<'de> for HashField<'de> {
+    type Error = serde_json::Error;
+
+    fn deserialize_any<V: serde::de::Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_borrowed_str(self.0)
+    }
+
+    fn deserialize_bool<V: serde::de::Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        match self.0 {
+            "1" => visitor.visit_bool(true),
+            "0" => visitor.visit_bool(false),
+            _ => visitor.visit_bool(self.parse("a bool")?),
+        }
+    }
+
+    parse_field! {
+        deserialize_i8 => visit_i8: i8,
+        deserialize_i16 => visit_i16: i16,
+        deserialize_i32 => visit_i32: i32,
+        deserialize_i64 => visit_i64: i64,
+        deserialize_u8 => visit_u8: u8,
+        deserialize_u16 => visit_u16: u16,
+        deserialize_u32 => visit_u32: u32,
+        deserialize_u64 => visit_u64: u64,
+        deserialize_f32 => visit_f32: f32,
+        deserialize_f64 => visit_f64: f64,
+    }
+
+    fn deserialize_option<V: serde::de::Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: serde::de::Visitor<'de>>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: serde::de::Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        serde::Deserializer::deserialize_seq(
+            &mut serde_json::Deserializer::from_str(self.0),
+            visitor,
+        )
+    }
+
+    fn deserialize_tuple<V: serde::de::Visitor<'de>>(
+        self,
+        _: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: serde::de::Visitor<'de>>(
+        self,
+        _: &'static str,
+        _: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V: serde::de::Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        serde::Deserializer::deserialize_map(
+            &mut serde_json::Deserializer::from_str(self.0),
+            visitor,
+        )
+    }
+
+    fn deserialize_struct<V: serde::de::Visitor<'de>>(
+        self,
+        _: &'static str,
+        _: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_map(visitor)
+    }
+
+    /// A unit variant is its name as is, any other variant is json.
+    fn deserialize_enum<V: serde::de::Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        use serde::de::IntoDeserializer;
+
+        match self.0.trim_start().starts_with('{') {
+            true => serde::Deserializer::deserialize_enum(
+                &mut serde_json::Deserializer::from_str(self.0),
+                name,
+                variants,
+                visitor,
+            ),
+            false => visitor.visit_enum(self.0.into_deserializer()),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        i128 u128 char str string bytes byte_buf unit unit_struct identifier ignored_any
+    }
+}
+
+/// The stream entry field [`JsonStream`] stores the json of each value under.
+pub const STREAM_FIELD: &str = "data";
+
+#[async_trait]
+pub trait JsonStream {
+    async fn json_xadd<Key: ToRedisArgs + Send + Sync, Val: Serialize + Send + Sync>(
+        &mut self,
+        stream: Key,
+        val: Val,
+    ) -> Result<String, JsonSetError>;
+    async fn json_xread<Key: ToRedisArgs + Send + Sync, Val: DeserializeOwned>(
+        &mut self,
+        stream: Key,
+        last_id: &str,
+        count: usize,
+    ) -> Result<Vec<(String, Val)>, JsonGetError>;
+}
+
+/// ```no_run
+/// extern crate redis_utils;
+/// extern crate redis;
+///
+/// use serde::{Deserialize, Serialize};
+/// use redis::RedisResult;
+/// use redis_utils::converters::JsonStream;
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct Event {
+///     kind: String,
+/// }
+///
+/// async fn stream_demo() -> RedisResult<()> {
+///     let mut con = redis::Client::open("redis://127.0.0.1/")?.get_async_connection().await?;
+///     let id = con.json_xadd("events", Event { kind: "signup".to_string() }).await.unwrap();
+///
+///     let events: Vec<(String, Event)> = con.json_xread("events", "0", 10).await.unwrap();
+///     for (id, event) in events {
+///         println!("{}: {}", id, event.kind);
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+#[async_trait]
+impl<C> JsonStream for C
+where
+    C: ConnectionLike + Send + Sync,
+{
+    /// serialize it to json -> xadd it under the `data` field, returning the id of the entry
+    async fn json_xadd<Key: ToRedisArgs + Send + Sync, Val: Serialize + Send + Sync>(
+        &mut self,
+        stream: Key,
+        val: Val,
+    ) -> Result<String, JsonSetError> {
+        Ok(redis::cmd("XADD")
+            .arg(stream)
+            .arg("*")
+            .arg(STREAM_FIELD)
+            .arg(serde_json::to_string(&val)?)
+            .query_async(self)
+            .await?)
+    }
+
+    /// xread up to `count` entries after `last_id` -> deserialize the `data` field of each entry
+    /// from json, alongside the id of the entry
+    async fn json_xread<Key: ToRedisArgs + Send + Sync, Val: DeserializeOwned>(
+        &mut self,
+        stream: Key,
+        last_id: &str,
+        count: usize,
+    ) -> Result<Vec<(String, Val)>, JsonGetError> {
+        type Entries = Vec<(String, Vec<String>)>;
+        let reply: Option<Vec<(String, Entries)>> = redis::cmd("XREAD")
+            .arg("COUNT")
+            .arg(count)
+            .arg("STREAMS")
+            .arg(stream)
+            .arg(last_id)
+            .query_async(self)
+            .await?;
+
+        let mut values = vec![];
+        for (_, entries) in reply.into_iter().flatten() {
+            for (id, fields) in entries {
+                let data = fields
+                    .chunks(2)
+                    .find(|pair| pair[0] == STREAM_FIELD)
+                    .and_then(|pair| pair.get(1))
+                    .ok_or_else(|| {
+                        serde::de::Error::custom(format!(
+                            "stream entry {} has no {} field",
+                            id, STREAM_FIELD
+                        ))
+                    })
+                    .map_err(JsonGetError::Serialization)?;
+                values.push((id, serde_json::from_str(data)?));
+            }
+        }
+        Ok(values)
+    }
+}
+
+/// A json document that carries its own version number, used for optimistic concurrency by
+/// [`JsonCas`].
+pub trait Versioned {
+    fn version(&self) -> u64;
+    fn set_version(&mut self, version: u64);
+}
+
+#[async_trait]
+pub trait JsonCas {
+    async fn json_cas<Key, Val, E>(
+        &mut self,
+        key: Key,
+        expected_version: u64,
+        new_val: Val,
+    ) -> Result<bool, TxError<E>>
+    where
+        Key: ToRedisArgs + Send + Sync,
+        Val: Versioned + Serialize + DeserializeOwned + Send + Sync,
+        E: Send;
+    async fn json_set_if_changed<Key, Val, E>(
+        &mut self,
+        key: Key,
+        val: Val,
+    ) -> Result<bool, TxError<E>>
+    where
+        Key: ToRedisArgs + Send + Sync,
+        Val: Serialize + Send + Sync,
+        E: Send;
+}
+
+/// ```no_run
+/// extern crate redis_utils;
+/// extern crate redis;
+///
+/// use serde::{Deserialize, Serialize};
+/// use redis::RedisResult;
+/// use redis_utils::converters::{JsonCas, Versioned};
+/// use redis_utils::TxError;
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct Account {
+///     version: u64,
+///     balance: i64,
+/// }
+///
+/// impl Versioned for Account {
+///     fn version(&self) -> u64 {
+///         self.version
+///     }
+///
+///     fn set_version(&mut self, version: u64) {
+///         self.version = version;
+///     }
+/// }
+///
+/// async fn cas_demo() -> RedisResult<()> {
+///     let client = redis::Client::open("redis://127.0.0.1/")?;
+///     let mut writer_a = client.get_async_connection().await?;
+///     let mut writer_b = client.get_async_connection().await?;
+///
+///     // both writers read version 1, only the first one to commit wins
+///     let a: Result<bool, TxError<()>> =
+///         writer_a.json_cas("account", 1, Account { version: 1, balance: 10 }).await;
+///     let b: Result<bool, TxError<()>> =
+///         writer_b.json_cas("account", 1, Account { version: 1, balance: 20 }).await;
+///
+///     assert!(matches!(a, Ok(true)));
+///     assert!(matches!(b, Ok(false)));
+///
+///     Ok(())
+/// }
+/// ```
+#[async_trait]
+impl<C> JsonCas for C
+where
+    C: ConnectionLike + Send + Sync,
+{
+    /// watch -> check the stored version is `expected_version` -> set `new_val` at the next
+    /// version. Returns `false` without writing if the version doesn't match or the key is missing.
+    /// Runs as a [`tx!`](crate::tx) with its default options.
+    ///
+    /// A writer whose `EXEC` fails because another one committed first reads the key again, and
+    /// finds the version it expected gone:
+    ///
+    /// ```
+    /// extern crate redis_utils;
+    /// extern crate redis;
+    ///
+    /// use serde::{Deserialize, Serialize};
+    /// use redis_utils::converters::{JsonCas, Versioned};
+    /// use redis_utils::TxError;
+    /// # use redis::Value;
+    /// # use redis_utils::mock::MockConnection;
+    ///
+    /// #[derive(Serialize, Deserialize)]
+    /// struct Account {
+    ///     version: u64,
+    ///     balance: i64,
+    /// }
+    ///
+    /// impl Versioned for Account {
+    ///     fn version(&self) -> u64 {
+    ///         self.version
+    ///     }
+    ///
+    ///     fn set_version(&mut self, version: u64) {
+    ///         self.version = version;
+    ///     }
+    /// }
+    ///
+    /// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+    /// # let stored = r#"{"version":1,"balance":0}"#;
+    /// # let mut writer_a = MockConnection::new().with_value("account", stored);
+    /// // writer b reads version 1 too, but writer a commits version 2 before its `EXEC`
+    /// # let read = Value::Data(stored.as_bytes().to_vec());
+    /// # let committed_by_a = r#"{"version":2,"balance":10}"#;
+    /// # let mut writer_b = MockConnection::new()
+    /// #     .reply("GET", read)
+    /// #     .conflicts(1)
+    /// #     .with_value("account", committed_by_a);
+    /// let a: Result<bool, TxError<()>> =
+    ///     writer_a.json_cas("account", 1, Account { version: 1, balance: 10 }).await;
+    /// let b: Result<bool, TxError<()>> =
+    ///     writer_b.json_cas("account", 1, Account { version: 1, balance: 20 }).await;
+    ///
+    /// assert!(matches!(a, Ok(true)));
+    /// assert_eq!(writer_a.value("account"), Some(r#"{"version":2,"balance":10}"#));
+    /// assert!(matches!(b, Ok(false)));
+    /// assert_eq!(writer_b.sent, vec!["WATCH", "GET", "EXEC", "WATCH", "GET", "UNWATCH"]);
+    /// assert_eq!(writer_b.value("account"), Some(r#"{"version":2,"balance":10}"#));
+    /// # });
+    /// ```
+    async fn json_cas<Key, Val, E>(
+        &mut self,
+        key: Key,
+        expected_version: u64,
+        mut new_val: Val,
+    ) -> Result<bool, TxError<E>>
+    where
+        Key: ToRedisArgs + Send + Sync,
+        Val: Versioned + Serialize + DeserializeOwned + Send + Sync,
+        E: Send,
+    {
+        new_val.set_version(expected_version + 1);
+        let new_string = serde_json::to_string(&new_val).map_err(TxError::Serialization)?;
+
+        let mut written = false;
+        let tx_result: Result<(), TxError<Infallible>> = crate::tx!(self, pipe, &key, {
+            let current: Option<Val> = self.maybe_json_get(&key).await?;
+            written = current.map(|val| val.version()) == Some(expected_version);
+            if written {
+                pipe.set(&key, &new_string).ignore();
+            }
+            // with nothing queued, a mismatch only un-watches the key
+            Ok(TxOutcome::Commit(&mut pipe))
+        });
+        tx_result
+            .map(|()| written)
+            .map_err(|err| err.map_abort(|never| match never {}))
+    }
+
+    /// watch -> get the stored json -> compare it with `val`, both in their [`canonical_json`]
+    /// form -> set the canonical json of `val` only if they differ. Returns whether it wrote, a
+    /// missing key (or one holding something that isn't json) counting as different. An unchanged
+    /// value isn't written at all, so it doesn't reset the expiry of the key nor fire keyspace
+    /// notifications. Runs as a [`tx!`](crate::tx) with its default options
+    ///
+    /// ```
+    /// extern crate redis_utils;
+    /// extern crate redis;
+    ///
+    /// use std::collections::BTreeMap;
+    /// use redis_utils::converters::JsonCas;
+    /// use redis_utils::TxError;
+    /// # use redis_utils::mock::MockConnection;
+    ///
+    /// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+    /// # let mut con = MockConnection::new().with_value("config", r#"{"b":2,"a":1}"#);
+    /// // stored as {"b":2,"a":1}
+    /// let same: BTreeMap<&str, u8> = vec![("a", 1), ("b", 2)].into_iter().collect();
+    /// let changed: Result<bool, TxError<()>> = con.json_set_if_changed("config", &same).await;
+    /// assert!(matches!(changed, Ok(false)));
+    /// assert_eq!(con.sent, vec!["WATCH", "GET", "UNWATCH"]);
+    ///
+    /// let other: BTreeMap<&str, u8> = vec![("a", 1), ("b", 3)].into_iter().collect();
+    /// let changed: Result<bool, TxError<()>> = con.json_set_if_changed("config", &other).await;
+    /// assert!(matches!(changed, Ok(true)));
+    /// assert_eq!(con.value("config"), Some(r#"{"a":1,"b":3}"#));
+    /// # });
+    /// ```
+    async fn json_set_if_changed<Key, Val, E>(
+        &mut self,
+        key: Key,
+        val: Val,
+    ) -> Result<bool, TxError<E>>
+    where
+        Key: ToRedisArgs + Send + Sync,
+        Val: Serialize + Send + Sync,
+        E: Send,
+    {
+        let new_string = canonical_json(&val).map_err(TxError::Serialization)?;
+
+        let mut written = false;
+        let tx_result: Result<(), TxError<Infallible>> = crate::tx!(self, pipe, &key, {
+            let current: Option<String> = self.get(&key).await?;
+            written = !current.is_some_and(|current| {
+                current == new_string
+                    || serde_json::from_str::<Value>(&current)
+                        .and_then(|current| canonical_json(&current))
+                        .is_ok_and(|current| current == new_string)
+            });
+            if written {
+                pipe.set(&key, &new_string).ignore();
+            }
+            // with nothing queued, an unchanged value only un-watches the key
+            Ok(TxOutcome::Commit(&mut pipe))
+        });
+        tx_result
+            .map(|()| written)
+            .map_err(|err| err.map_abort(|never| match never {}))
+    }
+}
+
+/// The abort value of the transactional helpers when the key they read from doesn't exist.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MissingSource;
+
+#[async_trait]
+pub trait JsonCopy {
+    async fn json_copy<Key, Val, F>(
+        &mut self,
+        src: Key,
+        dst: Key,
+        f: F,
+    ) -> Result<(), TxError<MissingSource>>
+    where
+        Key: ToRedisArgs + Send + Sync,
+        Val: Serialize + DeserializeOwned + Send + Sync,
+        F: FnMut(Val) -> Val + Send;
+    async fn json_rename<Key, Val, F>(
+        &mut self,
+        from: Key,
+        to: Key,
+        transform: Option<F>,
+    ) -> Result<(), TxError<MissingSource>>
+    where
+        Key: ToRedisArgs + Send + Sync,
+        Val: Serialize + DeserializeOwned + Send + Sync,
+        F: FnMut(Val) -> Val + Send;
+}
+
+/// ```no_run
+/// extern crate redis_utils;
+/// extern crate redis;
+///
+/// use serde::{Deserialize, Serialize};
+/// use redis::RedisResult;
+/// use redis_utils::converters::JsonCopy;
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct Settings {
+///     version: u32,
+/// }
+///
+/// async fn snapshot_demo() -> RedisResult<()> {
+///     let mut con = redis::Client::open("redis://127.0.0.1/")?.get_async_connection().await?;
+///     let copied = con.json_copy("settings", "settings:next", |mut settings: Settings| {
+///         settings.version += 1;
+///         settings
+///     })
+///     .await;
+///
+///     Ok(())
+/// }
+/// ```
+#[async_trait]
+impl<C> JsonCopy for C
+where
+    C: ConnectionLike + Send + Sync,
+{
+    /// watch both keys -> get `src` -> transform it with `f` -> set `dst`, aborting with
+    /// `MissingSource` if `src` doesn't exist. `f` runs again whenever the transaction is retried.
+    async fn json_copy<Key, Val, F>(
+        &mut self,
+        src: Key,
+        dst: Key,
+        mut f: F,
+    ) -> Result<(), TxError<MissingSource>>
+    where
+        Key: ToRedisArgs + Send + Sync,
+        Val: Serialize + DeserializeOwned + Send + Sync,
+        F: FnMut(Val) -> Val + Send,
+    {
+        crate::tx!(self, pipe, (&src, &dst), {
+            let val: Option<Val> = self.maybe_json_get(&src).await?;
+            match val {
+                Some(val) => Ok(TxOutcome::Commit(pipe.json_set(&dst, f(val))?.ignore())),
+                None => Ok(TxOutcome::Abort(MissingSource)),
+            }
+        })
+    }
+
+    /// moves `from` to `to`, aborting with `MissingSource` if `from` doesn't exist. Without a
+    /// `transform` that's a plain `RENAME`, with one it's a [`JsonCopy::json_copy`] that also
+    /// deletes `from` in the same transaction, unless `from` and `to` are the same key
+    ///
+    /// ```
+    /// extern crate redis_utils;
+    /// extern crate redis;
+    ///
+    /// use redis_utils::converters::{JsonCopy, JsonGet, JsonSet, MissingSource};
+    /// use redis_utils::TxError;
+    /// # use redis_utils::mock::MockConnection;
+    /// #
+    /// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+    /// # let mut con = MockConnection::new();
+    /// con.json_set("draft", 1).await.unwrap();
+    ///
+    /// // a plain RENAME
+    /// let renamed = con.json_rename("draft", "published", None::<fn(u32) -> u32>).await;
+    /// assert!(renamed.is_ok());
+    ///
+    /// // a transaction transforming the value on its way
+    /// let renamed = con.json_rename("published", "archived", Some(|version: u32| version + 1)).await;
+    /// assert!(renamed.is_ok());
+    /// assert_eq!(con.maybe_json_get("published").await.unwrap(), None::<u32>);
+    /// assert_eq!(con.json_get("archived").await.ok(), Some(2));
+    ///
+    /// // onto itself, the transformed value stays where it was
+    /// let renamed = con.json_rename("archived", "archived", Some(|version: u32| version + 1)).await;
+    /// assert!(renamed.is_ok());
+    /// assert_eq!(con.json_get("archived").await.ok(), Some(3));
+    ///
+    /// // both abort when there's nothing to move
+    /// let renamed = con.json_rename("draft", "published", None::<fn(u32) -> u32>).await;
+    /// assert!(matches!(renamed, Err(TxError::Abort(MissingSource))));
+    /// let renamed = con.json_rename("draft", "published", Some(|version: u32| version)).await;
+    /// assert!(matches!(renamed, Err(TxError::Abort(MissingSource))));
+    /// # });
+    /// ```
+    async fn json_rename<Key, Val, F>(
+        &mut self,
+        from: Key,
+        to: Key,
+        transform: Option<F>,
+    ) -> Result<(), TxError<MissingSource>>
+    where
+        Key: ToRedisArgs + Send + Sync,
+        Val: Serialize + DeserializeOwned + Send + Sync,
+        F: FnMut(Val) -> Val + Send,
+    {
+        let mut transform = match transform {
+            Some(transform) => transform,
+            None => {
+                return match self.rename(from, to).await {
+                    Err(err) if is_no_such_key(&err) => Err(TxError::Abort(MissingSource)),
+                    renamed => Ok(renamed?),
+                }
+            }
+        };
+
+        let same_key = from.to_redis_args() == to.to_redis_args();
+        crate::tx!(self, pipe, (&from, &to), {
+            let val: Option<Val> = self.maybe_json_get(&from).await?;
+            match val {
+                Some(val) => {
+                    pipe.json_set(&to, transform(val))?.ignore();
+                    // like `RENAME k k`, moving a key onto itself leaves it in place
+                    if !same_key {
+                        pipe.json_del(&from)?.ignore();
+                    }
+                    Ok(TxOutcome::Commit(&mut pipe))
+                }
+                None => Ok(TxOutcome::Abort(MissingSource)),
+            }
+        })
+    }
+}
+
+fn is_no_such_key(err: &RedisError) -> bool {
+    err.kind() == redis::ErrorKind::ResponseError
+        && err
+            .detail()
+            .is_some_and(|detail| detail.contains("no such key"))
+}
+
+/// The abort value of [`JsonCounter::json_incr_by`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CounterError {
+    /// The counter would go past its `max`, or past `i64::MAX` / `i64::MIN`.
+    Overflow,
+}
+
+#[async_trait]
+pub trait JsonCounter {
+    async fn json_incr_by<Key: ToRedisArgs + Send + Sync>(
+        &mut self,
+        key: Key,
+        delta: i64,
+        max: Option<i64>,
+    ) -> Result<i64, TxError<CounterError>>;
+    async fn json_field_incr<Key: ToRedisArgs + Send + Sync>(
+        &mut self,
+        key: Key,
+        field_path: &str,
+        delta: f64,
+    ) -> Result<f64, JsonGetError>;
+}
+
+/// Adds `ARGV[2]` to the number at the dot separated `ARGV[1]` in the json under `KEYS[1]`,
+/// returning the new number as a string (a Lua number would come back truncated to an integer),
+/// or nil if the key doesn't exist.
+const FIELD_INCR_SCRIPT: &str = r#"
+local json = redis.call('GET', KEYS[1])
+if not json then
+  return false
+end
+local doc = cjson.decode(json)
+local parts = {}
+for part in string.gmatch(ARGV[1], '[^.]+') do
+  table.insert(parts, part)
+end
+local node = doc
+for i = 1, #parts - 1 do
+  node = node[parts[i]]
+  if type(node) ~= 'table' then
+    return redis.error_reply('ERR ' .. parts[i] .. ' is not an object')
+  end
+end
+local field = parts[#parts]
+local current = node[field] or 0
+if type(current) ~= 'number' then
+  return redis.error_reply('ERR ' .. ARGV[1] .. ' is not a number')
+end
+local value = current + tonumber(ARGV[2])
+node[field] = value
+redis.call('SET', KEYS[1], cjson.encode(doc), 'KEEPTTL')
+return tostring(value)
+"#;
+
+/// The SHA1 digest of `FIELD_INCR_SCRIPT`, as returned by `SCRIPT LOAD` the first time it's used.
+static FIELD_INCR_SHA: OnceLock<String> = OnceLock::new();
+
+/// ```no_run
+/// extern crate redis_utils;
+/// extern crate redis;
+///
+/// use redis::RedisResult;
+/// use redis_utils::converters::{CounterError, JsonCounter};
+/// use redis_utils::TxError;
+///
+/// async fn counter_demo() -> RedisResult<()> {
+///     let mut con = redis::Client::open("redis://127.0.0.1/")?.get_async_connection().await?;
+///
+///     match con.json_incr_by("seats_taken", 1, Some(100)).await {
+///         Ok(taken) => println!("took seat {}", taken),
+///         Err(TxError::Abort(CounterError::Overflow)) => println!("sold out"),
+///         Err(_) => println!("something went wrong"),
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+#[async_trait]
+impl<C> JsonCounter for C
+where
+    C: ConnectionLike + Send + Sync,
+{
+    /// watch -> get the counter (0 if missing) -> add `delta` -> set it, aborting with
+    /// `CounterError::Overflow` instead if the result would exceed `max`. Returns the new value.
+    ///
+    /// An overflow writes nothing, and a write of another client between the read and the `EXEC`
+    /// re-runs the increment on what that client wrote:
+    ///
+    /// ```
+    /// extern crate redis_utils;
+    /// extern crate redis;
+    ///
+    /// use redis_utils::converters::{CounterError, JsonCounter};
+    /// use redis_utils::TxError;
+    /// # use redis::Value;
+    /// # use redis_utils::mock::MockConnection;
+    ///
+    /// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+    /// # let mut con = MockConnection::new().with_value("seats_taken", "100");
+    /// let taken = con.json_incr_by("seats_taken", 1, Some(100)).await;
+    /// assert!(matches!(taken, Err(TxError::Abort(CounterError::Overflow))));
+    /// assert_eq!(con.value("seats_taken"), Some("100"));
+    /// assert_eq!(con.sent, vec!["WATCH", "GET", "UNWATCH"]);
+    ///
+    /// // another client takes seat 42 after the first read of 41
+    /// # let mut con = MockConnection::new()
+    /// #     .reply("GET", Value::Data(b"41".to_vec()))
+    /// #     .conflicts(1)
+    /// #     .with_value("seats_taken", "42");
+    /// let taken = con.json_incr_by("seats_taken", 1, Some(100)).await;
+    /// assert!(matches!(taken, Ok(43)));
+    /// assert_eq!(con.value("seats_taken"), Some("43"));
+    /// assert_eq!(con.sent, vec!["WATCH", "GET", "EXEC", "WATCH", "GET", "EXEC", "UNWATCH"]);
+    /// # });
+    /// ```
+    async fn json_incr_by<Key: ToRedisArgs + Send + Sync>(
+        &mut self,
+        key: Key,
+        delta: i64,
+        max: Option<i64>,
+    ) -> Result<i64, TxError<CounterError>> {
+        let mut incremented = 0;
+        crate::tx!(self, pipe, &key, {
+            let current: Option<i64> = self.maybe_json_get(&key).await?;
+            let next = match (current.unwrap_or(0).checked_add(delta), max) {
+                (Some(next), Some(max)) if next > max => None,
+                (next, _) => next,
+            };
+
+            match next {
+                Some(next) => {
+                    incremented = next;
+                    Ok(TxOutcome::Commit(pipe.json_set(&key, next)?.ignore()))
+                }
+                None => Ok(TxOutcome::Abort(CounterError::Overflow)),
+            }
+        })
+        .map(|()| incremented)
+    }
+
+    /// `EVALSHA` a script that parses the json server side, adds `delta` to the number at
+    /// `field_path` (dot separated field names, a missing field counts as 0) and writes it back,
+    /// keeping the expiry of the key (redis 6 or later). Returns the new number, or
+    /// `JsonGetError::KeyMissing` if the key doesn't exist.
+    ///
+    /// Being a single script, it's atomic without any `WATCH` loop. The script is loaded with
+    /// `SCRIPT LOAD` on first use and sent whole with `EVAL` whenever the server doesn't know it
+    /// (say, after a restart or failover). The document is re-encoded by redis' `cjson`, which
+    /// doesn't keep the order of fields, writes numbers with 14 significant digits and turns empty
+    /// arrays into empty objects, keep it to documents where that doesn't matter
+    ///
+    /// ```no_run
+    /// extern crate redis_utils;
+    /// extern crate redis;
+    ///
+    /// use redis::RedisResult;
+    /// use redis_utils::converters::JsonCounter;
+    ///
+    /// async fn field_demo() -> RedisResult<()> {
+    ///     let mut con = redis::Client::open("redis://127.0.0.1/")?.get_async_connection().await?;
+    ///     let balance = con.json_field_incr("account:1", "balance.available", -12.5).await.unwrap();
+    ///     println!("{} left", balance);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    async fn json_field_incr<Key: ToRedisArgs + Send + Sync>(
+        &mut self,
+        key: Key,
+        field_path: &str,
+        delta: f64,
+    ) -> Result<f64, JsonGetError> {
+        let sha = match FIELD_INCR_SHA.get() {
+            Some(sha) => sha.clone(),
+            None => {
+                let sha: String = redis::cmd("SCRIPT")
+                    .arg("LOAD")
+                    .arg(FIELD_INCR_SCRIPT)
+                    .query_async(self)
+                    .await?;
+                FIELD_INCR_SHA.get_or_init(|| sha).clone()
+            }
+        };
+
+        let evalsha = redis::cmd("EVALSHA")
+            .arg(sha)
+            .arg(1)
+            .arg(&key)
+            .arg(field_path)
+            .arg(delta)
+            .query_async(self)
+            .await;
+        let value: Option<String> = match evalsha {
+            Err(err) if err.kind() == redis::ErrorKind::NoScriptError => {
+                redis::cmd("EVAL")
+                    .arg(FIELD_INCR_SCRIPT)
+                    .arg(1)
+                    .arg(&key)
+                    .arg(field_path)
+                    .arg(delta)
+                    .query_async(self)
+                    .await?
+            }
+            evalsha => evalsha?,
+        };
+
+        let value = value.ok_or_else(|| JsonGetError::missing(&key))?;
+        Ok(serde_json::from_str(&value)?)
+    }
+}
+
+/// Which keys the [`JsonScan`] helpers walk through, and how many `SCAN` asks for at a time.
+///
+/// The default matches every key holding a string, which is how every json value is stored, so
+/// scanning a keyspace with hashes or lists mixed in doesn't try to deserialize them. Filtering on
+/// the type needs redis 6 or later, set `type_filter` to `None` for older servers.
+///
+/// ```
+/// use redis::ToRedisArgs;
+/// use redis_utils::converters::ScanOptions;
+///
+/// let options = ScanOptions { count: 500, ..ScanOptions::new("cache:*") };
+/// assert_eq!(
+///     options.to_redis_args(),
+///     vec![&b"MATCH"[..], b"cache:*", b"COUNT", b"500", b"TYPE", b"string"]
+/// );
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScanOptions<'a> {
+    pub count: usize,
+    pub type_filter: Option<&'a str>,
+    pub match_pattern: String,
+}
+
+impl<'a> ScanOptions<'a> {
+    pub fn new<P: Into<String>>(match_pattern: P) -> Self {
+        ScanOptions {
+            match_pattern: match_pattern.into(),
+            ..Self::default()
+        }
+    }
+}
+
+impl<'a> Default for ScanOptions<'a> {
+    fn default() -> Self {
+        ScanOptions {
+            count: 100,
+            type_filter: Some("string"),
+            match_pattern: "*".to_string(),
+        }
+    }
+}
+
+impl<'a> ToRedisArgs for ScanOptions<'a> {
+    fn write_redis_args<W: ?Sized + RedisWrite>(&self, out: &mut W) {
+        ("MATCH", &self.match_pattern, "COUNT", self.count).write_redis_args(out);
+        if let Some(type_filter) = self.type_filter {
+            ("TYPE", type_filter).write_redis_args(out);
+        }
+    }
+}
+
+#[async_trait]
+pub trait JsonScan {
+    async fn json_scan_delete<Val, P>(
+        &mut self,
+        pattern: &str,
+        predicate: P,
+    ) -> Result<u64, JsonGetError>
+    where
+        Val: DeserializeOwned,
+        P: Fn(&Val) -> bool + Send + Sync;
+    async fn json_scan_delete_with<Val, P>(
+        &mut self,
+        options: &ScanOptions<'_>,
+        predicate: P,
+    ) -> Result<u64, JsonGetError>
+    where
+        Val: DeserializeOwned,
+        P: Fn(&Val) -> bool + Send + Sync;
+}
+
+/// ```no_run
+/// extern crate redis_utils;
+/// extern crate redis;
+///
+/// use serde::Deserialize;
+/// use redis::RedisResult;
+/// use redis_utils::converters::JsonScan;
+///
+/// #[derive(Deserialize)]
+/// struct CachedPage {
+///     author: String,
+/// }
+///
+/// async fn invalidate_demo() -> RedisResult<()> {
+///     let mut con = redis::Client::open("redis://127.0.0.1/")?.get_async_connection().await?;
+///     let deleted = con
+///         .json_scan_delete("cache:page:*", |page: &CachedPage| page.author == "parth")
+///         .await
+///         .unwrap();
+///
+///     Ok(())
+/// }
+/// ```
+#[async_trait]
+impl<C> JsonScan for C
+where
+    C: ConnectionLike + Send + Sync,
+{
+    /// [`JsonScan::json_scan_delete_with`] the default [`ScanOptions`] for `pattern`
+    async fn json_scan_delete<Val, P>(
+        &mut self,
+        pattern: &str,
+        predicate: P,
+    ) -> Result<u64, JsonGetError>
+    where
+        Val: DeserializeOwned,
+        P: Fn(&Val) -> bool + Send + Sync,
+    {
+        self.json_scan_delete_with(&ScanOptions::new(pattern), predicate)
+            .await
+    }
+
+    /// scan the keys matching `options` in batches -> mget each batch -> unlink (or del on servers
+    /// without `UNLINK`) the ones whose json deserializes into a `Val` satisfying `predicate`.
+    /// Returns how many keys were deleted. Keys that don't hold a string or whose json isn't a
+    /// `Val` are left alone.
+    async fn json_scan_delete_with<Val, P>(
+        &mut self,
+        options: &ScanOptions<'_>,
+        predicate: P,
+    ) -> Result<u64, JsonGetError>
+    where
+        Val: DeserializeOwned,
+        P: Fn(&Val) -> bool + Send + Sync,
+    {
+        let mut delete = "UNLINK";
+        let mut deleted = 0;
+        let mut cursor: u64 = 0;
+
+        loop {
+            let (next, keys): (u64, Vec<Vec<u8>>) = redis::cmd("SCAN")
+                .arg(cursor)
+                .arg(options)
+                .query_async(self)
+                .await?;
+
+            if !keys.is_empty() {
+                let strings: Vec<Option<String>> =
+                    redis::cmd("MGET").arg(&keys).query_async(self).await?;
+                let matched: Vec<&Vec<u8>> = keys
+                    .iter()
+                    .zip(strings)
+                    .filter(|(_, string)| {
+                        string
+                            .as_deref()
+                            .and_then(|string| serde_json::from_str::<Val>(string).ok())
+                            .is_some_and(|val| predicate(&val))
+                    })
+                    .map(|(key, _)| key)
+                    .collect();
+
+                if !matched.is_empty() {
+                    deleted += match redis::cmd(delete)
+                        .arg(&matched)
+                        .query_async::<_, u64>(self)
+                        .await
+                    {
+                        Err(err) if delete == "UNLINK" && is_unknown_command(&err) => {
+                            delete = "DEL";
+                            redis::cmd(delete).arg(&matched).query_async(self).await?
+                        }
+                        count => count?,
+                    };
+                }
+            }
+
+            if next == 0 {
+                return Ok(deleted);
+            }
+            cursor = next;
+        }
+    }
+}
+
+fn is_unknown_command(err: &RedisError) -> bool {
+    err.kind() == redis::ErrorKind::ResponseError
+        && err
+            .detail()
+            .is_some_and(|detail| detail.contains("unknown command"))
 }
@@ -0,0 +1,35 @@
+//! Helpers for inspecting what a transaction would send, without a redis to send it to.
+
+use redis::{Arg, Pipeline};
+
+/// Renders every command queued on `pipeline` as a line of space separated arguments, non utf-8
+/// bytes replaced. The `MULTI` / `EXEC` wrapping of an atomic pipeline isn't included since it's
+/// only added when the pipeline is sent.
+///
+/// ```
+/// use redis_utils::converters::PipelineJsonSet;
+/// use redis_utils::debug::pipeline_debug;
+/// use redis_utils::TxError;
+///
+/// let mut pipe = redis::pipe();
+/// pipe.atomic();
+/// let queued: Result<_, TxError<()>> = pipe.json_set("key1", vec![1, 2]);
+/// assert!(queued.is_ok());
+/// pipe.del("key2");
+///
+/// assert_eq!(pipeline_debug(&pipe), vec!["SET key1 [1,2]", "DEL key2"]);
+/// ```
+pub fn pipeline_debug(pipeline: &Pipeline) -> Vec<String> {
+    pipeline
+        .cmd_iter()
+        .map(|cmd| {
+            cmd.args_iter()
+                .map(|arg| match arg {
+                    Arg::Simple(bytes) => String::from_utf8_lossy(bytes).into_owned(),
+                    Arg::Cursor => "<cursor>".to_string(),
+                })
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .collect()
+}
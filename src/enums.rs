@@ -0,0 +1,159 @@
+//! Fieldless enums stored as their bare name or discriminant instead of as json.
+//!
+//! Through [`JsonSet::json_set`](crate::converters::JsonSet::json_set) a `Status::Active` is stored
+//! as `"Active"`, quotes included. A [`RedisEnum`] is stored as `Active` (or `1` with its
+//! discriminant), which is shorter, what other clients expect of a status, and with integers
+//! stored in redis' compact integer encoding. With the `derive` feature, `#[derive(RedisEnum)]`
+//! implements it.
+//!
+//! The two representations don't mix: [`EnumStore::enum_get`] fails on a value written as json,
+//! and `json_get` fails on one written by [`EnumStore::enum_set`] (unless it's stored as an
+//! integer, which happens to be valid json). Pick one per key, and read statuses other clients
+//! write with `enum_get`.
+//!
+//! ```
+//! extern crate redis_utils;
+//! extern crate redis;
+//!
+//! use std::borrow::Cow;
+//! use redis_utils::converters::JsonGetError;
+//! use redis_utils::enums::{EnumStore, RedisEnum};
+//! # use redis_utils::mock::MockConnection;
+//!
+//! #[derive(Debug, PartialEq)]
+//! enum Status {
+//!     Active,
+//!     Suspended,
+//! }
+//!
+//! impl RedisEnum for Status {
+//!     fn to_stored(&self) -> Cow<'static, str> {
+//!         match self {
+//!             Status::Active => "active".into(),
+//!             Status::Suspended => "suspended".into(),
+//!         }
+//!     }
+//!
+//!     fn from_stored(stored: &str) -> Option<Self> {
+//!         match stored {
+//!             "active" => Some(Status::Active),
+//!             "suspended" => Some(Status::Suspended),
+//!             _ => None,
+//!         }
+//!     }
+//! }
+//!
+//! # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+//! # let mut con = MockConnection::new();
+//! con.enum_set("user:1:status", &Status::Suspended).await.unwrap();
+//! assert_eq!(con.value("user:1:status"), Some("suspended"));
+//!
+//! let status: Status = con.enum_get("user:1:status").await.unwrap();
+//! assert_eq!(status, Status::Suspended);
+//!
+//! let missing = con.enum_get::<_, Status>("user:2:status").await;
+//! assert!(matches!(missing, Err(JsonGetError::KeyMissing { .. })));
+//! # });
+//! ```
+
+use crate::converters::{JsonGetError, JsonSetError};
+use async_trait::async_trait;
+use redis::aio::ConnectionLike;
+use redis::{AsyncCommands, ToRedisArgs};
+use std::borrow::Cow;
+
+/// A fieldless enum stored as a short string of its own, see [`crate::enums`].
+pub trait RedisEnum: Sized {
+    /// What the variant is stored as, e.g. `active` or `1`.
+    fn to_stored(&self) -> Cow<'static, str>;
+
+    /// The variant stored as `stored`, `None` if there's none.
+    fn from_stored(stored: &str) -> Option<Self>;
+}
+
+#[async_trait]
+pub trait EnumStore {
+    async fn enum_set<Key: ToRedisArgs + Send + Sync, Val: RedisEnum + Sync>(
+        &mut self,
+        key: Key,
+        val: &Val,
+    ) -> Result<(), JsonSetError>;
+    async fn enum_get<Key: ToRedisArgs + Send + Sync, Val: RedisEnum>(
+        &mut self,
+        key: Key,
+    ) -> Result<Val, JsonGetError>;
+}
+
+#[async_trait]
+impl<C> EnumStore for C
+where
+    C: ConnectionLike + Send + Sync,
+{
+    /// set the stored form of the variant, as is
+    async fn enum_set<Key: ToRedisArgs + Send + Sync, Val: RedisEnum + Sync>(
+        &mut self,
+        key: Key,
+        val: &Val,
+    ) -> Result<(), JsonSetError> {
+        let stored = val.to_stored();
+        Ok(self.set(key, stored.as_ref()).await?)
+    }
+
+    /// get -> the variant stored as that. A key that doesn't exist is a
+    /// `JsonGetError::KeyMissing`, a value that isn't a variant (say, one written as json) a
+    /// `JsonGetError::Serialization`
+    async fn enum_get<Key: ToRedisArgs + Send + Sync, Val: RedisEnum>(
+        &mut self,
+        key: Key,
+    ) -> Result<Val, JsonGetError> {
+        use serde::de::Error as _;
+
+        let stored: Option<String> = self.get(&key).await?;
+        let stored = stored.ok_or_else(|| JsonGetError::missing(&key))?;
+        Val::from_stored(&stored).ok_or_else(|| {
+            let hint = match stored.starts_with('"') {
+                true => ", it looks like json written by json_set",
+                false => "",
+            };
+            JsonGetError::Serialization(serde_json::Error::custom(format!(
+                "{:?} is not a stored variant of {}{}",
+                stored,
+                std::any::type_name::<Val>(),
+                hint
+            )))
+        })
+    }
+}
+
+/// Implements [`RedisEnum`] for a fieldless enum, storing each variant as its name.
+///
+/// + `#[redis(repr = "int")]` stores the discriminants instead (`0`, `1`, or the explicit
+///   `= 3`s).
+/// + `#[redis(rename = "on")]` on a variant stores it under another name.
+/// + `#[redis(crate = "ru")]` points the generated code at a renamed `redis_utils` dependency.
+///
+/// ```
+/// use redis_utils::enums::RedisEnum;
+///
+/// #[derive(Debug, PartialEq, RedisEnum)]
+/// enum Status {
+///     Active,
+///     #[redis(rename = "off")]
+///     Suspended,
+/// }
+///
+/// #[derive(Debug, PartialEq, RedisEnum)]
+/// #[redis(repr = "int")]
+/// enum Priority {
+///     Low = 1,
+///     High = 10,
+/// }
+///
+/// assert_eq!(Status::Active.to_stored(), "Active");
+/// assert_eq!(Status::from_stored("off"), Some(Status::Suspended));
+/// assert_eq!(Priority::High.to_stored(), "10");
+/// assert_eq!(Priority::from_stored("1"), Some(Priority::Low));
+/// assert_eq!(Priority::from_stored("2"), None);
+/// ```
+#[cfg(feature = "derive")]
+pub use redis_utils_derive::RedisEnum;
@@ -0,0 +1,156 @@
+//! Telling a connection left dirty by a cancelled transaction apart from a clean one.
+//!
+//! Dropping the future of a [`tx!`](crate::tx) (a request timing out or being cancelled, a
+//! `select!` picking another branch) stops it wherever it's at, and there's no async drop to clean
+//! up with. Dropped after `WATCH` and before `EXEC`, it leaves the keys watched on the connection,
+//! and the next transaction on it can fail its `EXEC` because of a key it never meant to watch.
+//! Dropped during a round trip, it leaves a reply unread on a plain `aio::Connection`, which then
+//! hands that reply to the next command instead of its own.
+//!
+//! A [`GuardedConnection`] keeps track of both by watching the commands going through it, and
+//! reports the connection as poisoned until [`GuardedConnection::reset`] cleans it up, or for
+//! good if a reply was left unread. Check it before reusing a connection, typically when checking
+//! it back into a pool. `MultiplexedConnection` and `ConnectionManager` read every reply even if
+//! its request was dropped, but a watch left behind is a hazard on them just the same.
+
+use crate::reset_tx_state;
+use redis::aio::ConnectionLike;
+use redis::{Arg, Cmd, ErrorKind, Pipeline, RedisFuture, RedisResult, Value};
+
+/// A connection keeping track of whether a dropped request or transaction left it dirty.
+///
+/// ```
+/// #[macro_use] extern crate redis_utils;
+/// extern crate redis;
+///
+/// use futures_util::FutureExt;
+/// use redis_utils::guard::GuardedConnection;
+/// use redis_utils::TxError;
+/// # use redis::{ErrorKind, RedisError};
+/// # use redis_utils::mock::MockConnection;
+/// #
+/// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+/// # let detail = "DISCARD without MULTI".to_string();
+/// # let outside_multi = RedisError::from((ErrorKind::ResponseError, "server error", detail));
+/// # let redis_con = MockConnection::new().fail("DISCARD", outside_multi);
+/// let mut con = GuardedConnection::new(redis_con);
+///
+/// let tx = async {
+///   let tx_result: Result<(), TxError<()>> = tx!(&mut con, pipe, &["key1"], {
+///     std::future::pending::<()>().await;
+///     Ok(&mut pipe)
+///   });
+///   tx_result
+/// };
+/// // the transaction is dropped while its body waits, after it watched `key1`
+/// assert!(tx.now_or_never().is_none());
+/// assert!(con.is_poisoned());
+///
+/// con.reset().await.unwrap();
+/// assert!(!con.is_poisoned());
+/// # });
+/// ```
+pub struct GuardedConnection<C> {
+    con: C,
+    watching: bool,
+    interrupted: bool,
+}
+
+impl<C: ConnectionLike + Send> GuardedConnection<C> {
+    pub fn new(con: C) -> Self {
+        GuardedConnection {
+            con,
+            watching: false,
+            interrupted: false,
+        }
+    }
+
+    /// Whether the connection shouldn't be reused as it is: it has keys watched outside of a
+    /// running transaction, or a reply left unread.
+    pub fn is_poisoned(&self) -> bool {
+        self.watching || self.interrupted
+    }
+
+    /// Whether a `WATCH` went through without an `EXEC`, `UNWATCH` or `DISCARD` after it.
+    pub fn is_watching(&self) -> bool {
+        self.watching
+    }
+
+    /// Whether a request was dropped before its reply came in. On a plain `aio::Connection` the
+    /// reply is then waiting to be read in place of the next one, drop the connection instead of
+    /// reusing it.
+    pub fn is_interrupted(&self) -> bool {
+        self.interrupted
+    }
+
+    /// Drops the watched keys and any open `MULTI`, see [`reset_tx_state`]. It can't do anything
+    /// about an unread reply, so a connection that [`is_interrupted`](Self::is_interrupted) stays
+    /// poisoned.
+    pub async fn reset(&mut self) -> RedisResult<()> {
+        reset_tx_state(self).await
+    }
+
+    pub fn get_ref(&self) -> &C {
+        &self.con
+    }
+
+    /// The connection, whatever state it's in.
+    pub fn into_inner(self) -> C {
+        self.con
+    }
+}
+
+fn name(cmd: &Cmd) -> Option<&[u8]> {
+    match cmd.args_iter().next() {
+        Some(Arg::Simple(name)) => Some(name),
+        _ => None,
+    }
+}
+
+impl<C: ConnectionLike + Send> ConnectionLike for GuardedConnection<C> {
+    fn req_packed_command<'a>(&'a mut self, cmd: &'a Cmd) -> RedisFuture<'a, Value> {
+        Box::pin(async move {
+            self.interrupted = true;
+            let reply = self.con.req_packed_command(cmd).await;
+            self.interrupted = false;
+
+            // any reply from redis, errors included, means the command ran
+            let replied = reply
+                .as_ref()
+                .map_or_else(|err| err.kind() != ErrorKind::IoError, |_| true);
+            let name = name(cmd).map(<[u8]>::to_ascii_uppercase);
+            match name.as_deref() {
+                Some(b"WATCH") if reply.is_ok() => self.watching = true,
+                Some(b"UNWATCH") | Some(b"EXEC") | Some(b"DISCARD") if replied => {
+                    self.watching = false
+                }
+                _ => {}
+            }
+            reply
+        })
+    }
+
+    fn req_packed_commands<'a>(
+        &'a mut self,
+        cmd: &'a Pipeline,
+        offset: usize,
+        count: usize,
+    ) -> RedisFuture<'a, Vec<Value>> {
+        Box::pin(async move {
+            self.interrupted = true;
+            let replies = self.con.req_packed_commands(cmd, offset, count).await;
+            self.interrupted = false;
+
+            // the replies of an atomic pipeline are offset by its `MULTI` and queued commands, its
+            // `EXEC` went through once they came in
+            if offset > 0 && replies.is_ok() {
+                self.watching = false;
+            }
+            replies
+        })
+    }
+
+    fn get_db(&self) -> i64 {
+        self.con.get_db()
+    }
+}
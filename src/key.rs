@@ -0,0 +1,121 @@
+//! Structured keys, rendered to their string form in one place.
+//!
+//! A [`RedisKey`] type knows the key it stands for, and [`redis_key_args!`](crate::redis_key_args)
+//! makes it a `ToRedisArgs` written as that key, so it can be handed as is to every helper taking
+//! keys, the watched keys of a [`tx!`](crate::tx) included:
+//!
+//! ```
+//! #[macro_use] extern crate redis_utils;
+//! extern crate redis;
+//!
+//! use redis::ToRedisArgs;
+//! use redis_utils::key::RedisKey;
+//!
+//! struct UserId(u64);
+//!
+//! impl RedisKey for UserId {
+//!     fn to_key(&self) -> String {
+//!         format!("user:{}", self.0)
+//!     }
+//! }
+//!
+//! enum Counter {
+//!     Visits,
+//!     Signups { day: u32 },
+//! }
+//!
+//! impl RedisKey for Counter {
+//!     fn to_key(&self) -> String {
+//!         match self {
+//!             Counter::Visits => "counter:visits".to_string(),
+//!             Counter::Signups { day } => ("counter:signups", day).to_key(),
+//!         }
+//!     }
+//! }
+//!
+//! redis_key_args!(UserId, Counter);
+//!
+//! assert_eq!(UserId(123).to_redis_args(), vec![b"user:123".to_vec()]);
+//! assert_eq!(
+//!     (&[UserId(1), UserId(2)]).to_redis_args(),
+//!     vec![b"user:1".to_vec(), b"user:2".to_vec()]
+//! );
+//! assert_eq!(Counter::Signups { day: 7 }.to_key(), "counter:signups:7");
+//! ```
+//!
+//! Tuples render as their parts joined with `:`, but since a tuple is already a `ToRedisArgs`
+//! written as one argument per part, pass `.to_key()` of a tuple where a single key is meant.
+
+/// A type that stands for a redis key.
+pub trait RedisKey {
+    /// The key, e.g. `user:123`.
+    fn to_key(&self) -> String;
+}
+
+impl RedisKey for str {
+    fn to_key(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl RedisKey for String {
+    fn to_key(&self) -> String {
+        self.clone()
+    }
+}
+
+impl<T: RedisKey + ?Sized> RedisKey for &T {
+    fn to_key(&self) -> String {
+        (**self).to_key()
+    }
+}
+
+macro_rules! display_key {
+    ($($ty:ty),*) => {
+        $(
+            impl RedisKey for $ty {
+                fn to_key(&self) -> String {
+                    self.to_string()
+                }
+            }
+        )*
+    };
+}
+
+display_key!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, char, bool);
+
+macro_rules! tuple_key {
+    ($first:ident $(, $rest:ident)*) => {
+        impl<$first: RedisKey, $($rest: RedisKey),*> RedisKey for ($first, $($rest),*) {
+            #[allow(non_snake_case)]
+            fn to_key(&self) -> String {
+                let ($first, $($rest),*) = self;
+                let parts: Vec<String> = vec![$first.to_key() $(, $rest.to_key())*];
+                parts.join(":")
+            }
+        }
+    };
+}
+
+tuple_key!(A, B);
+tuple_key!(A, B, C);
+tuple_key!(A, B, C, D);
+tuple_key!(A, B, C, D, E);
+
+/// Implements `ToRedisArgs` for [`RedisKey`](crate::key::RedisKey) types, writing each of them as
+/// the single argument its `to_key` renders, see [`key`](crate::key).
+#[macro_export]
+macro_rules! redis_key_args {
+    ($($ty:ty),+ $(,)?) => {
+        $(
+            impl $crate::redis::ToRedisArgs for $ty {
+                fn write_redis_args<W>(&self, out: &mut W)
+                where
+                    W: ?Sized + $crate::redis::RedisWrite,
+                {
+                    out.write_arg($crate::key::RedisKey::to_key(self).as_bytes())
+                }
+            }
+        )+
+    };
+}
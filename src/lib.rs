@@ -13,14 +13,14 @@
 //!
 //! + A connection
 //! + The name of a [pipeline](https://docs.rs/redis/latest/redis/struct.Pipeline.html) which it configures
-//! in [atomic-mode](https://docs.rs/redis/latest/redis/struct.Pipeline.html#method.atomic).
+//!   in [atomic-mode](https://docs.rs/redis/latest/redis/struct.Pipeline.html#method.atomic).
 //! + A set of keys to `WATCH`
 //! + The body of the transaction that can get those keys, use the pipeline (for side effects) and if those keys change (and
-//! the `EXEC` component of the atomic pipeline fails), then the body will be re-executed.
+//!   the `EXEC` component of the atomic pipeline fails), then the body will be re-executed.
 //! + Allows for safe early returns (aborted transactions) with typed values, all keys will be un-watched during an early
-//! return.
+//!   return.
 //!
-//! ```rust
+//! ```ignore
 //! tx!(&mut con, pipe, &["key1"], {
 //!   let mut value: u8 = con.get("key1").await?;
 //!   value = value + 1;
@@ -31,13 +31,13 @@
 //!
 //! ## Aborting a tx
 //!
-//! ```rust
+//! ```ignore
 //! tx!(&mut con, pipe, &["key1"], {
 //!   let mut value: u8 = con.get("key1").await?;
 //!   value = value + 1;
 //!
 //!   if value == 69 {
-//!     return Err(Abort(BadNumberFound));
+//!     return Err(TxError::Abort(BadNumberFound));
 //!   }
 //!
 //!   Ok(pipe.set("key1", value))
@@ -46,13 +46,13 @@
 //!
 //! ## Handling return values
 //!
-//! ```rust
+//! ```ignore
 //! let tx: Result<u8, TxError<NumberError> > = tx!(&mut con, pipe, &["key1"], {
 //!   let mut value: u8 = con.get("key1").await?;
 //!   value = value + 1;
 //!
 //!   if value == 69 {
-//!     return Err(Abort(BadNumberFound));
+//!     return Err(TxError::Abort(BadNumberFound));
 //!   }
 //!
 //!   Ok(pipe.set("key1", value))
@@ -62,44 +62,118 @@
 //! + The `Ok(T)` of `tx` is the type that's handed to `pipe.set()` for `redis-rs`'s type inference.
 //! + `TxError` allows you to return any type in `TxError::Abort` for custom type handling.
 //! + If the transaction fails due to an underlying `redis` error or `serde` `tx` will reflect this in the
-//! associated `TxError::DbError` or `TxError::Serialization`.
+//!   associated `TxError::DbError` or `TxError::Serialization`.
 //!
 //! # JSON helpers
 //!
 //! Using the helpers from [TODO](converters) allow you to turn this:
 //!
-//! ```rust
+//! ```ignore
 //! let json_string: String = con.get(key).await?;
 //! let value: Type = serde_json::from_str(&json_string).unwrap;
 //!  ```
 //!
-//! ```rust
+//! ```ignore
 //! let value: Type = con.json_get(key).await.unwrap();
 //! ```
 //!
+//! # Features
+//!
+//! + `tokio-comp` (default): async connections on tokio, every helper needs it.
+//! + `deadpool` / `bb8`: transactions over pooled connections, see [`pool`].
+//! + `redisjson`: helpers for the RedisJSON module, see `redisjson`.
+//! + `schema`: validating values against their json schema before writing them, see `schema`.
+//! + `derive`: `#[derive(RedisJson)]`, see [`stored`], and `#[derive(RedisEnum)]`, see [`enums`].
+//! + `client-cache`: a client-side cache for `json_get`, invalidated through `CLIENT TRACKING`,
+//!   see `cache`.
+//! + `time` / `chrono`: RFC 3339 timestamps in json values, see `timestamp`.
+//! + `latency`: `tx_with_latency!`, timing the round trips of a transaction.
+//!
+
+use crate::converters::{JsonGetError, JsonSetError};
+use redis::aio::ConnectionLike;
+use redis::RedisResult;
+use std::convert::Infallible;
 
-use crate::converters::JsonGetError;
+#[doc(hidden)]
+pub use redis;
 
+#[cfg(not(feature = "tokio-comp"))]
+compile_error!(
+    "redis_utils needs an async runtime for redis-rs: enable its `tokio-comp` feature, which is on \
+     by default unless `default-features = false` is set"
+);
+
+pub mod batch;
+#[cfg(feature = "client-cache")]
+pub mod cache;
+pub mod cluster;
+pub mod context;
 pub mod converters;
+pub mod debug;
+pub mod enums;
+pub mod guard;
+pub mod key;
+#[cfg(feature = "test-mock")]
+#[doc(hidden)]
+pub mod mock;
+pub mod namespace;
+pub mod options;
+#[cfg(any(feature = "deadpool", feature = "bb8"))]
+pub mod pool;
+pub mod prelude;
+#[cfg(feature = "redisjson")]
+pub mod redisjson;
+#[cfg(feature = "schema")]
+pub mod schema;
+pub mod stored;
+#[cfg(any(feature = "time", feature = "chrono"))]
+pub mod timestamp;
+pub mod transaction;
 
+/// `WATCH`es `$keys`, breaking out of the enclosing loop with a `TxError::DbError` (or
+/// `TxError::InMulti`) if that fails.
+/// Together with [`unwatch!`] this is the building block for hand written transaction loops.
+///
+/// ```no_run
+/// #![no_implicit_prelude]
+/// extern crate redis_utils as ru;
+/// extern crate redis;
+/// extern crate std;
+///
+/// use std::result::Result::{self, Ok};
+///
+/// async fn watch_demo(con: &mut redis::aio::Connection) {
+///     let watched: Result<(), ru::TxError<()>> = loop {
+///         ru::watch!(con, &["key1"]);
+///         ru::unwatch!(con);
+///         break Ok(());
+///     };
+/// }
+/// ```
 #[macro_export]
 macro_rules! watch {
     ($conn:expr, $keys:expr) => {
-        if let Err(e) = redis::cmd("WATCH")
+        if let ::core::result::Result::Err(e) = $crate::redis::cmd("WATCH")
             .arg($keys)
             .query_async::<_, ()>($conn)
             .await
         {
-            break Err(DbError(e));
+            break ::core::result::Result::Err($crate::TxError::from_watch_error(e));
         }
     };
 }
 
+/// `UNWATCH`es every key, breaking out of the enclosing loop with a `TxError::DbError` if that
+/// fails.
 #[macro_export]
 macro_rules! unwatch {
     ($conn:expr) => {
-        if let Err(e) = redis::cmd("UNWATCH").query_async::<_, ()>($conn).await {
-            break Err(DbError(e));
+        if let ::core::result::Result::Err(e) = $crate::redis::cmd("UNWATCH")
+            .query_async::<_, ()>($conn)
+            .await
+        {
+            break ::core::result::Result::Err($crate::TxError::DbError(e));
         }
     };
 }
@@ -112,12 +186,17 @@ macro_rules! unwatch {
 ///
 /// + A connection
 /// + The name of a [pipeline](https://docs.rs/redis/latest/redis/struct.Pipeline.html) which it configures
-/// in [atomic-mode](https://docs.rs/redis/latest/redis/struct.Pipeline.html#method.atomic).
+///   in [atomic-mode](https://docs.rs/redis/latest/redis/struct.Pipeline.html#method.atomic).
 /// + A set of keys to `WATCH`
 /// + The body of the transaction that can get those keys, use the pipeline (for side effects) and if those keys change (and
-/// the `EXEC` component of the atomic pipeline fails), then the body will be re-executed.
+///   the `EXEC` component of the atomic pipeline fails), then the body will be re-executed.
 /// + Allows for safe early returns (aborted transactions) with typed values, all keys will be un-watched during an early
-/// return.
+///   return.
+///
+/// [`transaction::run_tx`] and [`transaction::Transaction`] run the same loop with a closure in
+/// place of the body, for bodies that move values around or spawn futures.
+/// On a cluster, where `WATCH` can't be relied on, see [`cluster::cluster_tx`] for a
+/// script-based counterpart.
 ///
 ///```no_run
 /// #[macro_use] extern crate redis_utils;
@@ -141,6 +220,9 @@ macro_rules! unwatch {
 ///
 /// ## Aborting a tx
 ///
+/// Return a `TxError::Abort` from the body, or `?` a foreign error after turning it into one with
+/// [`TxResultExt::or_abort`].
+///
 ///```no_run
 /// #[macro_use] extern crate redis_utils;
 /// extern crate redis;
@@ -155,7 +237,7 @@ macro_rules! unwatch {
 ///       value = value + 1;
 ///
 ///       if value == 69 {
-///         return Err(Abort("BadNumberFound"));
+///         return Err(TxError::Abort("BadNumberFound"));
 ///       }
 ///
 ///       Ok(pipe.set("key1", value))
@@ -165,70 +247,1024 @@ macro_rules! unwatch {
 /// }
 /// ```
 ///
+/// ## Reading several keys
+///
+/// The body is awaited before the pipeline is executed, so it can freely read through the
+/// connection, hold on to what it read, and only then decide what to queue on the pipeline:
+///
+///```no_run
+/// #[macro_use] extern crate redis_utils;
+/// extern crate redis;
+///
+/// use redis::{RedisResult, AsyncCommands};
+/// use redis_utils::TxError;
+///
+/// async fn tx_demo() -> RedisResult<()> {
+///     let mut con = redis::Client::open("redis://127.0.0.1/")?.get_async_connection().await?;
+///     let tx_result: Result<(), TxError<()>> = tx!(&mut con, pipe, &["balance", "limit"], {
+///       let balance: i64 = con.get("balance").await?;
+///       let limit: i64 = con.get("limit").await?;
+///
+///       if balance > limit {
+///         pipe.set("over_limit", balance - limit).ignore();
+///       }
+///
+///       Ok(&mut pipe)
+///     });
+///
+///    Ok(())
+/// }
+/// ```
+///
 /// + The `Ok(T)` of `tx` is the type that's handed to `pipe.set()` for `redis-rs`'s type inference.
 /// + `TxError` allows you to return any type in `TxError::Abort` for custom type handling.
 /// + If the transaction fails due to an underlying `redis` error or `serde` `tx` will reflect this in the
-/// associated `TxError::DbError` or `TxError::Serialization`.
+///   associated `TxError::DbError` or `TxError::Serialization`.
+///
+/// ## Nil replies
+///
+/// A transaction is only retried when `EXEC` itself replies nil because a watched key changed, a
+/// command of the pipeline replying nil is handed to the result like any other reply:
+///
+/// ```
+/// #[macro_use] extern crate redis_utils;
+/// extern crate redis;
+///
+/// use redis::AsyncCommands;
+/// use redis_utils::TxError;
+/// # use redis_utils::mock::MockConnection;
+/// #
+/// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+/// // `EXEC` fails once, then succeeds with `GET` replying nil
+/// let mut con = MockConnection::new().conflicts(1);
+/// let mut runs = 0;
+///
+/// let tx_result: Result<(Option<u8>,), TxError<()>> = tx!(&mut con, pipe, &["missing"], {
+///   runs += 1;
+///   Ok(pipe.get("missing"))
+/// });
+///
+/// assert!(matches!(tx_result, Ok((None,))));
+/// assert_eq!(runs, 2);
+/// # });
+/// ```
+///
+/// ## Returning the pipeline
+///
+/// The body has to hand back the pipeline the macro named and made atomic. Any other pipeline
+/// would run its commands without `MULTI`/`EXEC`, unguarded by the watched keys, so debug builds
+/// panic on one before sending anything:
+///
+/// ```should_panic
+/// #[macro_use] extern crate redis_utils;
+/// extern crate redis;
+///
+/// use redis_utils::TxError;
+/// # use redis_utils::mock::MockConnection;
+/// #
+/// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+/// # let mut con = MockConnection::new();
+/// let mut batch = redis::pipe();
+/// let tx_result: Result<(), TxError<()>> = tx!(&mut con, pipe, &["key1"], {
+///   // panics: `batch` isn't `pipe`
+///   Ok(batch.set("key1", 1).ignore())
+/// });
+/// # });
+/// ```
+///
+/// [`transaction::Transaction`] all but rules this out: its body can only commit a pipeline
+/// borrowed for as long as its future, the one it's lent, and debug builds check that too.
+///
+/// ## Committing nothing
+///
+/// A body that finds there's nothing to write can hand back the pipeline without queuing anything.
+/// No `MULTI`/`EXEC` is sent for it, the keys are un-watched and the transaction succeeds with the
+/// replies of no commands (`()`, an empty `Vec`, ...). Whatever the body read is handed out through
+/// the variables it captures:
+///
+/// ```
+/// #[macro_use] extern crate redis_utils;
+/// extern crate redis;
+///
+/// use redis::AsyncCommands;
+/// use redis_utils::TxError;
+/// # use redis_utils::mock::MockConnection;
+/// #
+/// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+/// # let mut con = MockConnection::new().with_value("stock", "5");
+/// let mut stock = 0;
+/// let tx_result: Result<(), TxError<()>> = tx!(&mut con, pipe, &["stock"], {
+///   stock = con.get("stock").await?;
+///   if stock > 0 {
+///     // already stocked, nothing to write
+///     return Ok(&mut pipe);
+///   }
+///   Ok(pipe.set("stock", 10).ignore())
+/// });
+///
+/// assert!(tx_result.is_ok());
+/// assert_eq!(stock, 5);
+/// assert_eq!(con.sent, vec!["WATCH", "GET", "UNWATCH"]);
+/// # });
+/// ```
+///
+/// ## Failing bodies
+///
+/// The keys are un-watched whenever the body fails, redis errors included, so the connection can
+/// be handed to the next user (say, back to a pool) without stale watches:
+///
+/// ```
+/// #[macro_use] extern crate redis_utils;
+/// extern crate redis;
+///
+/// use redis::{ErrorKind, RedisError};
+/// use redis_utils::TxError;
+/// # use redis_utils::mock::MockConnection;
+/// #
+/// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+/// # let mut con = MockConnection::new();
+/// let tx_result: Result<(), TxError<()>> = tx!(&mut con, pipe, &["key1"], {
+///   let failed = RedisError::from((ErrorKind::TypeError, "not a number"));
+///   Err::<&mut redis::Pipeline, _>(TxError::DbError(failed))
+/// });
+///
+/// assert!(matches!(tx_result, Err(TxError::DbError(_))));
+/// assert_eq!(con.sent, vec!["WATCH", "UNWATCH"]);
+/// # });
+/// ```
+///
+/// ## Cancellation
+///
+/// The future of a transaction is not cancellation safe. Dropped before it completes (a request
+/// timing out, a `select!` taking another branch), it stops wherever it's at without cleaning up:
+/// keys watched before the drop stay watched and can fail the next transaction on the connection,
+/// and a round trip cut short leaves its reply unread on a plain `aio::Connection`. Either
+/// drop the connection along with the transaction, call [`reset_tx_state`] on it before reusing
+/// it, or wrap it in a [`guard::GuardedConnection`], which tells whether it was left dirty. The
+/// `timeout`, `deadline` and `cancel` options stop a transaction without dropping it, and un-watch
+/// its keys on the way out.
+///
+/// ## Renamed dependency
+///
+/// The macro only refers to this crate through `$crate` and brings nothing into the scope of the
+/// body, so it keeps working when the dependency is renamed, with no prelude in sight:
+///
+/// ```no_run
+/// #![no_implicit_prelude]
+/// extern crate redis_utils as ru;
+/// extern crate redis;
+/// extern crate std;
+///
+/// use redis::AsyncCommands;
+/// use std::result::Result::{self, Ok};
+///
+/// async fn tx_demo(con: &mut redis::aio::Connection) {
+///     let tx_result: Result<u8, ru::TxError<()>> = ru::tx!(con, pipe, &["key1"], {
+///       let value: u8 = con.get("key1").await?;
+///       Ok(pipe.set("key1", value + 1))
+///     });
+/// }
+/// ```
+///
+/// ## Options
+///
+/// Trailing `name = value` pairs configure the transaction, see [`options`] for what's available.
+/// For instance `timeout = Duration::from_secs(2)` fails the transaction with `TxError::Timeout`
+/// if `WATCH`, `EXEC` or `UNWATCH` take longer than 2 seconds.
 ///
 #[macro_export]
 macro_rules! tx {
-    ($conn:expr, $pipe_name:ident, $keys:expr, $body:expr) => {{
-        use redis::pipe;
-        use redis::Pipeline;
-        use redis_utils::TxError;
-        use redis_utils::TxError::{Abort, DbError, Serialization};
-        use redis_utils::{unwatch, watch};
-
-        let ret: Result<_, TxError<_>> = loop {
-            watch!($conn, $keys);
-
-            let mut $pipe_name = pipe();
-            $pipe_name.atomic();
-
-            let create_tx: Result<&mut Pipeline, TxError<_>> = async { $body }.await;
-
-            let pipeline: &mut Pipeline = match create_tx {
-                Ok(pipeline) => pipeline,
-                Err(Abort(value)) => {
-                    unwatch!($conn);
-                    break Err(Abort(value));
-                }
-                Err(Serialization(value)) => {
-                    unwatch!($conn);
-                    break Err(Serialization(value));
-                }
-                Err(DbError(red_err)) => break Err(DbError(red_err)),
-            };
+    ($conn:expr, $pipe_name:ident, $keys:expr, $body:expr $(, $option:ident = $value:expr)* $(,)?) => {{
+        let mut attempts: u32 = 0;
+        let ret = $crate::__tx!(attempts; $conn, $pipe_name, $keys, $body $(, $option = $value)*);
+        let _ = attempts;
+        ret
+    }};
+}
 
-            let tx_success: Option<_> = pipeline.query_async($conn).await?;
+/// [`tx!`] that also tells how the transaction went, returning a [`TxSuccess`] in place of the
+/// bare value. It takes the same arguments and options.
+///
+/// ```
+/// #[macro_use] extern crate redis_utils;
+/// extern crate redis;
+///
+/// use redis::{AsyncCommands, Value};
+/// use redis_utils::{TxError, TxSuccess};
+/// # use redis_utils::mock::MockConnection;
+/// #
+/// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+/// // a watched key changes once before `EXEC`
+/// let mut con = MockConnection::new().conflicts(1).reply("INCRBY", Value::Int(1));
+///
+/// let tx_result: Result<TxSuccess<(u8,)>, TxError<()>> = tx_with_meta!(&mut con, pipe, &["key1"], {
+///   Ok(pipe.incr("key1", 1))
+/// });
+///
+/// let success = tx_result.ok().unwrap();
+/// assert_eq!(success, TxSuccess { value: (1,), attempts: 2, retried: true });
+/// # });
+/// ```
+#[macro_export]
+macro_rules! tx_with_meta {
+    ($conn:expr, $pipe_name:ident, $keys:expr, $body:expr $(, $option:ident = $value:expr)* $(,)?) => {{
+        let mut attempts: u32 = 0;
+        let ret = $crate::__tx!(attempts; $conn, $pipe_name, $keys, $body $(, $option = $value)*);
+        ret.map(|value| $crate::TxSuccess { value, attempts, retried: attempts > 1 })
+    }};
+}
 
-            if let Some(response) = tx_success {
-                unwatch!($conn);
-                break Ok(response);
+/// [`tx_with_meta!`] that also tells where the time went, returning the [`TxLatency`] of the
+/// transaction along with its [`TxSuccess`]. Enabled with the `latency` feature, without it
+/// nothing is timed.
+///
+/// ```
+/// #[macro_use] extern crate redis_utils;
+/// extern crate redis;
+///
+/// use redis::{AsyncCommands, Value};
+/// use redis_utils::{TxError, TxLatency, TxSuccess};
+/// # use redis_utils::mock::MockConnection;
+/// #
+/// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+/// // a watched key changes once before `EXEC`
+/// let mut con = MockConnection::new().conflicts(1).reply("INCRBY", Value::Int(1));
+///
+/// let tx_result: Result<(TxSuccess<(u8,)>, TxLatency), TxError<()>> = tx_with_latency!(&mut con, pipe, &["key1"], {
+///   Ok(pipe.incr("key1", 1))
+/// });
+///
+/// let (success, latency) = tx_result.ok().unwrap();
+/// assert_eq!(success.attempts, 2);
+/// // a slow transaction spending little of its time on redis is slow because of its body or its
+/// // retries, not because of redis
+/// assert!(latency.redis() <= latency.total);
+/// # });
+/// ```
+#[cfg(feature = "latency")]
+#[macro_export]
+macro_rules! tx_with_latency {
+    ($conn:expr, $pipe_name:ident, $keys:expr, $body:expr $(, $option:ident = $value:expr)* $(,)?) => {{
+        let mut attempts: u32 = 0;
+        let options = $crate::options::TxOptions::new()$(.$option($value))*;
+        let ret = $crate::__tx!(@run attempts, options; $conn, $pipe_name, $keys, $body);
+        let latency = options.latency();
+        ret.map(|value| ($crate::TxSuccess { value, attempts, retried: attempts > 1 }, latency))
+    }};
+}
+
+/// The loop behind [`tx!`] and [`tx_with_meta!`], counting the runs of the body in `$attempts`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __tx {
+    ($attempts:ident; $conn:expr, $pipe_name:ident, $keys:expr, $body:expr $(, $option:ident = $value:expr)*) => {{
+        let options = $crate::options::TxOptions::new()$(.$option($value))*;
+        $crate::__tx!(@run $attempts, options; $conn, $pipe_name, $keys, $body)
+    }};
+    (@run $attempts:ident, $options:ident; $conn:expr, $pipe_name:ident, $keys:expr, $body:expr) => {{
+        let options = &$options;
+
+        let mut retries = 0;
+        let ret = loop {
+            let mut conflicts = 0;
+            let attempt: ::core::result::Result<_, $crate::TxError<_>> = match options.ping($conn).await {
+                ::core::result::Result::Err(err) => ::core::result::Result::Err(err),
+                ::core::result::Result::Ok(()) => loop {
+                    if let ::core::result::Result::Err(err) = options.watch($conn, $keys).await {
+                        break ::core::result::Result::Err(err);
+                    }
+
+                    let mut $pipe_name = $crate::redis::pipe();
+                    $pipe_name.atomic();
+                    // an address rather than a pointer, which would make the future `!Send`
+                    let configured = &$pipe_name as *const $crate::redis::Pipeline as usize;
+
+                    $attempts += 1;
+                    let create_tx = options.within_deadline(async { $body }).await;
+
+                    let pipeline: &mut $crate::redis::Pipeline = match options.settle($conn, create_tx).await {
+                        ::core::result::Result::Ok(::core::option::Option::Some(pipeline)) => pipeline,
+                        ::core::result::Result::Ok(::core::option::Option::None) => match options.conflicted(&mut conflicts).await {
+                            ::core::result::Result::Ok(()) => continue,
+                            ::core::result::Result::Err(err) => break ::core::result::Result::Err(err),
+                        },
+                        ::core::result::Result::Err(err) => break ::core::result::Result::Err(err),
+                    };
+                    ::core::debug_assert!(
+                        pipeline as *const $crate::redis::Pipeline as usize == configured,
+                        "the body of the transaction returned a pipeline other than `{}`, which wouldn't be atomic",
+                        ::core::stringify!($pipe_name),
+                    );
+
+                    let tx_success: ::core::option::Option<_> = match options.exec($conn, pipeline).await {
+                        ::core::result::Result::Ok(tx_success) => tx_success,
+                        ::core::result::Result::Err(err) => break ::core::result::Result::Err(err),
+                    };
+
+                    if let ::core::option::Option::Some(response) = tx_success {
+                        if let ::core::result::Result::Err(err) = options.unwatch($conn).await {
+                            break ::core::result::Result::Err(err);
+                        }
+                        break ::core::result::Result::Ok(response);
+                    }
+                    if let ::core::result::Result::Err(err) = options.conflicted(&mut conflicts).await {
+                        break ::core::result::Result::Err(err);
+                    }
+                },
+            };
+
+            if !options.recover(&attempt, &mut retries).await {
+                break attempt;
             }
         };
-        ret
+        options.replicate($conn, ret).await
     }};
 }
 
+/// Adds `keys` to the keys watched by the transaction running on `con`, from inside its body, for
+/// when which keys to watch depends on what the body read first.
+///
+/// `WATCH` only guards against changes made after it's issued, so watch a key *before* reading it:
+/// a key read first and watched afterwards could have changed in between without the transaction
+/// noticing. Reading through keys that are already watched (like an index) to find the next keys
+/// to watch is safe, if the index changes the `EXEC` fails anyway. Every watch is dropped by the
+/// `EXEC` (or `UNWATCH`) that ends an attempt, so a retry re-runs the body, which watches the keys
+/// it needs this time around. With the `namespace` option, wrap the keys with the same
+/// [`namespace::Namespace`], they aren't prefixed here.
+///
+/// ```no_run
+/// #[macro_use] extern crate redis_utils;
+/// extern crate redis;
+///
+/// use redis::{RedisResult, AsyncCommands};
+/// use redis_utils::{watch_more, TxError};
+///
+/// async fn tx_demo() -> RedisResult<()> {
+///     let mut con = redis::Client::open("redis://127.0.0.1/")?.get_async_connection().await?;
+///     let tx_result: Result<(), TxError<()>> = tx!(&mut con, pipe, &["index"], {
+///       let members: Vec<String> = con.smembers("index").await?;
+///       watch_more(&mut con, &members).await?;
+///
+///       for member in members {
+///         let count: u32 = con.get(&member).await?;
+///         pipe.set(&member, count + 1).ignore();
+///       }
+///
+///       Ok(&mut pipe)
+///     });
+///
+///    Ok(())
+/// }
+/// ```
+pub async fn watch_more<C, Key, T>(con: &mut C, keys: Key) -> Result<(), TxError<T>>
+where
+    C: ConnectionLike,
+    Key: redis::ToRedisArgs,
+{
+    redis::cmd("WATCH")
+        .arg(keys)
+        .query_async::<_, ()>(con)
+        .await
+        .map_err(TxError::from_watch_error)
+}
+
+/// Returns a connection to a clean state after a transaction was interrupted, e.g. because it
+/// panicked or its future was dropped while keys were watched or a `MULTI` was open. Issues a
+/// `DISCARD` and an `UNWATCH`, it's safe to call on a connection that's already clean, which
+/// makes it a good fit for checking connections back into a pool.
+///
+/// ```no_run
+/// use redis::RedisResult;
+/// use redis_utils::reset_tx_state;
+///
+/// async fn recycle(con: &mut redis::aio::Connection) -> RedisResult<()> {
+///     reset_tx_state(con).await
+/// }
+/// ```
+pub async fn reset_tx_state<C: ConnectionLike>(con: &mut C) -> RedisResult<()> {
+    match redis::cmd("DISCARD").query_async::<_, ()>(con).await {
+        Err(err) if !is_discard_without_multi(&err) => return Err(err),
+        _ => {}
+    }
+    redis::cmd("UNWATCH").query_async(con).await
+}
+
+fn is_discard_without_multi(err: &redis::RedisError) -> bool {
+    err.kind() == redis::ErrorKind::ResponseError
+        && err
+            .detail()
+            .is_some_and(|detail| detail.contains("without MULTI"))
+}
+
 /// Represents the various ways a transaction can return early. It could be `Abort`ed early due to
 /// some precondition failure. It could fail due to a `Serialization` error if you're using any of
 /// the `redis_utils::converters`. Or if there is an underlying `RedisError`.
 pub enum TxError<T> {
     Abort(T),
+    /// Aborted for several reasons at once, usually collected with an [`Aborts`].
+    AbortMany(Vec<T>),
     Serialization(serde_json::Error),
     DbError(redis::RedisError),
+    /// `WATCH` failed because the connection was already in a `MULTI`, usually left there by a
+    /// transaction that never got to `EXEC` or `DISCARD` on a shared connection. Call
+    /// [`reset_tx_state`] on the connection before reusing it, the original error is kept.
+    ///
+    /// ```
+    /// #[macro_use] extern crate redis_utils;
+    /// extern crate redis;
+    ///
+    /// use redis_utils::{reset_tx_state, TxError};
+    /// # use redis::{ErrorKind, RedisError};
+    /// # use redis_utils::mock::MockConnection;
+    /// #
+    /// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+    /// # let detail = "WATCH inside MULTI is not allowed".to_string();
+    /// # let in_multi = RedisError::from((ErrorKind::ResponseError, "server error", detail));
+    /// # let mut con = MockConnection::new().fail("WATCH", in_multi);
+    /// let tx_result: Result<(), TxError<()>> = tx!(&mut con, pipe, &["key1"], {
+    ///   Ok(&mut pipe)
+    /// });
+    ///
+    /// if let Err(TxError::InMulti(_)) = tx_result {
+    ///     let _ = reset_tx_state(&mut con).await;
+    /// }
+    /// # assert!(matches!(tx_result, Err(TxError::InMulti(_))));
+    /// # });
+    /// ```
+    InMulti(redis::RedisError),
+    /// The `PING` sent before the transaction by the `ping_first` option failed, the connection is
+    /// most likely dead and should be replaced.
+    PingFailed(redis::RedisError),
+    /// A round trip to redis or the whole transaction took longer than allowed by the `timeout` or
+    /// `deadline` options.
+    Timeout,
+    /// The token given to the `cancel` option was cancelled.
+    Cancelled,
+    /// The transaction committed, but only `acked` of the `required` replicas acknowledged it
+    /// before the timeout of the `wait` option.
+    ReplicationTimeout {
+        acked: usize,
+        required: usize,
+    },
+    /// The strategy of the `retry_strategy` option gave up after the body ran `attempts` times in
+    /// a row, each time failing because a watched key changed (or with a `TxOutcome::Retry`).
+    MaxRetriesExceeded {
+        attempts: u32,
+    },
 }
 
+/// A transaction that went through, as returned by [`tx_with_meta!`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TxSuccess<T> {
+    pub value: T,
+    /// How many times the body ran: once, plus once per optimistic-lock failure, `TxOutcome::Retry`
+    /// and `retry_on_error` retry.
+    pub attempts: u32,
+    /// Whether the body ran more than once.
+    pub retried: bool,
+}
+
+/// Where the time of a transaction went, as returned by [`tx_with_latency!`]. The round trips are
+/// summed over every attempt.
+#[cfg(feature = "latency")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TxLatency {
+    /// From the start of the transaction to its end, retries, backoffs and the body included.
+    pub total: std::time::Duration,
+    pub watch: std::time::Duration,
+    pub exec: std::time::Duration,
+    pub unwatch: std::time::Duration,
+}
+
+#[cfg(feature = "latency")]
+impl TxLatency {
+    /// The time spent waiting on the `WATCH`, `EXEC` and `UNWATCH` round trips.
+    pub fn redis(&self) -> std::time::Duration {
+        self.watch + self.exec + self.unwatch
+    }
+}
+
+/// The value of a committed transaction along with the raw replies to its commands, for when some
+/// of them reply with something a typed value can't hold (or not in the same shape every time).
+/// Use it as the return type of [`tx!`], or inside the [`TxSuccess`] of [`tx_with_meta!`] to also
+/// know how many attempts it took.
+///
+/// `replies` are what `EXEC` replied, minus the replies of the commands marked `ignore`d.
+///
+/// ```
+/// #[macro_use] extern crate redis_utils;
+/// extern crate redis;
+///
+/// use redis::Value;
+/// use redis_utils::{TxCommit, TxError, TxSuccess};
+/// # use redis_utils::mock::MockConnection;
+/// #
+/// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+/// # let mut con = MockConnection::new().reply("INCRBY", Value::Int(3)).with_value("key2", "a");
+/// let tx_result: Result<TxSuccess<TxCommit<(u8, ())>>, TxError<()>> = tx_with_meta!(&mut con, pipe, &["key1"], {
+///   Ok(pipe.incr("key1", 1).cmd("MGET").arg("key2").arg("key3"))
+/// });
+///
+/// let success = tx_result.ok().unwrap();
+/// assert_eq!(success.attempts, 1);
+/// assert_eq!(success.value.value.0, 3);
+/// assert_eq!(
+///     success.value.replies[1],
+///     Value::Bulk(vec![Value::Data(b"a".to_vec()), Value::Nil])
+/// );
+/// # });
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct TxCommit<T> {
+    pub value: T,
+    pub replies: Vec<redis::Value>,
+}
+
+impl<T: redis::FromRedisValue> redis::FromRedisValue for TxCommit<T> {
+    fn from_redis_value(v: &redis::Value) -> RedisResult<Self> {
+        match v {
+            redis::Value::Bulk(replies) => Ok(TxCommit {
+                value: T::from_redis_value(v)?,
+                replies: replies.clone(),
+            }),
+            _ => Err(redis::RedisError::from((
+                redis::ErrorKind::TypeError,
+                "the replies of a transaction are an array",
+                format!("{:?}", v),
+            ))),
+        }
+    }
+}
+
+/// What the body of a transaction decided to do. The body can hand back a `TxOutcome` in place of
+/// the pipeline, a bare `&mut Pipeline` is the same as `TxOutcome::Commit`.
+///
+/// + `Commit` executes the pipeline, re-running the body if a watched key changed.
+/// + `Abort` un-watches the keys and fails the transaction with `TxError::Abort`.
+/// + `AbortMany` does the same with `TxError::AbortMany`, for several reasons at once.
+/// + `Retry` un-watches the keys and re-runs the body straight away, for when the body itself
+///   finds what it read to be inconsistent.
+///
+///```no_run
+/// #[macro_use] extern crate redis_utils;
+/// extern crate redis;
+///
+/// use redis::{RedisResult, AsyncCommands};
+/// use redis_utils::{TxError, TxOutcome};
+///
+/// async fn tx_demo() -> RedisResult<()> {
+///     let mut con = redis::Client::open("redis://127.0.0.1/")?.get_async_connection().await?;
+///     let tx_result: Result<u8, TxError<&str>> = tx!(&mut con, pipe, &["index", "count"], {
+///       let index: Vec<String> = con.smembers("index").await?;
+///       let count: usize = con.get("count").await?;
+///
+///       if index.len() != count {
+///         return Ok(TxOutcome::Retry);
+///       }
+///
+///       if count == 69 {
+///         return Ok(TxOutcome::Abort("BadNumberFound"));
+///       }
+///
+///       Ok(TxOutcome::Commit(pipe.incr("count", 1)))
+///     });
+///
+///    Ok(())
+/// }
+/// ```
+pub enum TxOutcome<'p, T> {
+    Commit(&'p mut redis::Pipeline),
+    Abort(T),
+    AbortMany(Vec<T>),
+    Retry,
+}
+
+/// Collects the reasons to abort a transaction as the body checks its preconditions, to report
+/// every one that failed instead of only the first. [`finish`](Aborts::finish) fails with a
+/// `TxError::AbortMany` holding all of them if there are any, which un-watches the keys like any
+/// other abort.
+///
+/// ```
+/// #[macro_use] extern crate redis_utils;
+/// extern crate redis;
+///
+/// use redis_utils::{Aborts, TxError};
+/// # use redis_utils::mock::MockConnection;
+///
+/// #[derive(Debug, PartialEq)]
+/// enum Invalid {
+///     NameTaken,
+///     EmailTaken,
+///     TooYoung,
+/// }
+///
+/// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+/// # let mut con = MockConnection::new();
+/// # let (name_taken, email_taken, age) = (true, true, 30);
+/// let tx_result: Result<(), TxError<Invalid>> = tx!(&mut con, pipe, &["names", "emails"], {
+///   let mut aborts = Aborts::new();
+///   aborts.ensure(!name_taken, Invalid::NameTaken);
+///   aborts.ensure(!email_taken, Invalid::EmailTaken);
+///   aborts.ensure(age >= 18, Invalid::TooYoung);
+///   aborts.finish()?;
+///
+///   Ok(pipe.sadd("names", "ada").ignore().sadd("emails", "ada@example.com").ignore())
+/// });
+///
+/// assert!(matches!(
+///     tx_result,
+///     Err(TxError::AbortMany(reasons)) if reasons == vec![Invalid::NameTaken, Invalid::EmailTaken]
+/// ));
+/// assert_eq!(con.sent, vec!["WATCH", "UNWATCH"]);
+/// # });
+/// ```
+pub struct Aborts<T> {
+    reasons: Vec<T>,
+}
+
+impl<T> Default for Aborts<T> {
+    fn default() -> Self {
+        Aborts { reasons: vec![] }
+    }
+}
+
+impl<T> Aborts<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, reason: T) {
+        self.reasons.push(reason);
+    }
+
+    /// Collects `reason` unless `holds`.
+    pub fn ensure(&mut self, holds: bool, reason: T) {
+        if !holds {
+            self.push(reason);
+        }
+    }
+
+    /// The value of `result`, collecting its error as a reason instead.
+    pub fn check<V>(&mut self, result: Result<V, T>) -> Option<V> {
+        match result {
+            Ok(value) => Some(value),
+            Err(reason) => {
+                self.push(reason);
+                None
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.reasons.is_empty()
+    }
+
+    /// Fails with every reason collected, if there's any.
+    pub fn finish(self) -> Result<(), TxError<T>> {
+        match self.reasons.is_empty() {
+            true => Ok(()),
+            false => Err(TxError::AbortMany(self.reasons)),
+        }
+    }
+}
+
+impl<'p, T> From<&'p mut redis::Pipeline> for TxOutcome<'p, T> {
+    fn from(pipeline: &'p mut redis::Pipeline) -> Self {
+        TxOutcome::Commit(pipeline)
+    }
+}
+
+impl<T> TxError<T> {
+    /// Converts the `Abort` payload with `f` (every one of them for `AbortMany`), leaving the
+    /// `Serialization` and `DbError` variants as they are.
+    ///
+    /// ```
+    /// use redis_utils::TxError;
+    ///
+    /// let err: TxError<u8> = TxError::Abort(69);
+    /// let err: TxError<String> = err.map_abort(|value| format!("bad number: {}", value));
+    ///
+    /// assert_eq!(err.abort(), Some("bad number: 69".to_string()));
+    /// ```
+    pub fn map_abort<U, F: FnMut(T) -> U>(self, mut f: F) -> TxError<U> {
+        match self {
+            TxError::Abort(value) => TxError::Abort(f(value)),
+            TxError::AbortMany(values) => TxError::AbortMany(values.into_iter().map(f).collect()),
+            TxError::Serialization(err) => TxError::Serialization(err),
+            TxError::DbError(err) => TxError::DbError(err),
+            TxError::InMulti(err) => TxError::InMulti(err),
+            TxError::PingFailed(err) => TxError::PingFailed(err),
+            TxError::Timeout => TxError::Timeout,
+            TxError::Cancelled => TxError::Cancelled,
+            TxError::ReplicationTimeout { acked, required } => {
+                TxError::ReplicationTimeout { acked, required }
+            }
+            TxError::MaxRetriesExceeded { attempts } => TxError::MaxRetriesExceeded { attempts },
+        }
+    }
+
+    /// Tells a `WATCH` issued inside a `MULTI` apart from any other redis error.
+    #[doc(hidden)]
+    pub fn from_watch_error(err: redis::RedisError) -> Self {
+        let inside_multi = err.kind() == redis::ErrorKind::ResponseError
+            && err
+                .detail()
+                .is_some_and(|detail| detail.contains("inside MULTI"));
+        if inside_multi {
+            TxError::InMulti(err)
+        } else {
+            TxError::DbError(err)
+        }
+    }
+
+    /// Returns the `Abort` payload, if the transaction was aborted for a single reason.
+    pub fn abort(self) -> Option<T> {
+        match self {
+            TxError::Abort(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Returns every reason the transaction was aborted for, none if it failed some other way.
+    pub fn aborts(self) -> Vec<T> {
+        match self {
+            TxError::Abort(value) => vec![value],
+            TxError::AbortMany(values) => values,
+            _ => vec![],
+        }
+    }
+
+    /// Folds every variant into an application error type that knows how to represent each of
+    /// them. An `AbortMany` is handed over as its first reason, match it beforehand to keep all of
+    /// them (an empty one as a `ResponseError`). A `Timeout` is handed over as a timed out I/O
+    /// `RedisError`, a `Cancelled` as an interrupted one, a `ReplicationTimeout` as a
+    /// `ResponseError` telling how many replicas acknowledged the transaction, and a
+    /// `MaxRetriesExceeded` as an `ExecAbortError` telling how many attempts it took.
+    ///
+    /// ```
+    /// use redis_utils::TxError;
+    ///
+    /// enum AppError {
+    ///     BadNumber(u8),
+    ///     Storage(String),
+    /// }
+    ///
+    /// impl From<u8> for AppError {
+    ///     fn from(value: u8) -> Self {
+    ///         AppError::BadNumber(value)
+    ///     }
+    /// }
+    ///
+    /// impl From<serde_json::Error> for AppError {
+    ///     fn from(err: serde_json::Error) -> Self {
+    ///         AppError::Storage(err.to_string())
+    ///     }
+    /// }
+    ///
+    /// impl From<redis::RedisError> for AppError {
+    ///     fn from(err: redis::RedisError) -> Self {
+    ///         AppError::Storage(err.to_string())
+    ///     }
+    /// }
+    ///
+    /// let err: AppError = TxError::Abort(69).into_error();
+    /// assert!(matches!(err, AppError::BadNumber(69)));
+    /// ```
+    pub fn into_error<E>(self) -> E
+    where
+        E: From<T> + From<serde_json::Error> + From<redis::RedisError>,
+    {
+        match self {
+            TxError::Abort(value) => E::from(value),
+            TxError::AbortMany(values) => match values.into_iter().next() {
+                Some(value) => E::from(value),
+                None => E::from(redis::RedisError::from((
+                    redis::ErrorKind::ResponseError,
+                    "transaction was aborted without a reason",
+                ))),
+            },
+            TxError::Serialization(err) => E::from(err),
+            TxError::DbError(err) | TxError::InMulti(err) | TxError::PingFailed(err) => {
+                E::from(err)
+            }
+            TxError::Timeout => E::from(redis::RedisError::from(std::io::Error::from(
+                std::io::ErrorKind::TimedOut,
+            ))),
+            TxError::Cancelled => E::from(redis::RedisError::from(std::io::Error::from(
+                std::io::ErrorKind::Interrupted,
+            ))),
+            TxError::ReplicationTimeout { acked, required } => E::from(redis::RedisError::from((
+                redis::ErrorKind::ResponseError,
+                "transaction was not replicated in time",
+                format!("{} of {} replicas acknowledged it", acked, required),
+            ))),
+            TxError::MaxRetriesExceeded { attempts } => E::from(redis::RedisError::from((
+                redis::ErrorKind::ExecAbortError,
+                "transaction gave up retrying",
+                format!("a watched key changed on all {} attempts", attempts),
+            ))),
+        }
+    }
+}
+
+impl<T: std::fmt::Display> std::fmt::Display for TxError<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TxError::Abort(value) => write!(f, "transaction aborted: {}", value),
+            TxError::AbortMany(values) => {
+                let values: Vec<String> = values.iter().map(ToString::to_string).collect();
+                write!(f, "transaction aborted: {}", values.join("; "))
+            }
+            TxError::Serialization(err) => {
+                write!(f, "transaction failed to (de)serialize a value: {}", err)
+            }
+            TxError::DbError(err) => write!(f, "transaction failed with a redis error: {}", err),
+            TxError::InMulti(err) => write!(
+                f,
+                "transaction started on a connection already in a MULTI: {}",
+                err
+            ),
+            TxError::PingFailed(err) => write!(f, "transaction found its connection dead: {}", err),
+            TxError::Timeout => write!(f, "transaction timed out"),
+            TxError::Cancelled => write!(f, "transaction was cancelled"),
+            TxError::ReplicationTimeout { acked, required } => write!(
+                f,
+                "transaction was only acknowledged by {} of {} replicas",
+                acked, required
+            ),
+            TxError::MaxRetriesExceeded { attempts } => write!(
+                f,
+                "transaction gave up retrying, a watched key changed on all {} attempts",
+                attempts
+            ),
+        }
+    }
+}
+
+/// Shortcuts for handling the `Result` a transaction returns.
+pub trait TxResult<T, E> {
+    /// Maps the error into an application error type, see [`TxError::into_error`].
+    fn into_app_result<E2>(self) -> Result<T, E2>
+    where
+        E2: From<E> + From<serde_json::Error> + From<redis::RedisError>;
+
+    /// Keeps only the `Abort` payload as the error, for callers that treat every other failure as
+    /// a bug. An `AbortMany` keeps its first reason.
+    ///
+    /// # Panics
+    ///
+    /// If the transaction failed for any reason other than an `Abort`, or with an empty
+    /// `AbortMany`.
+    ///
+    /// ```
+    /// use redis_utils::{TxError, TxResult};
+    ///
+    /// let tx_result: Result<u8, TxError<&str>> = Err(TxError::Abort("BadNumberFound"));
+    /// assert_eq!(tx_result.unwrap_abort(), Err("BadNumberFound"));
+    /// ```
+    fn unwrap_abort(self) -> Result<T, E>;
+}
+
+impl<T, E> TxResult<T, E> for Result<T, TxError<E>> {
+    fn into_app_result<E2>(self) -> Result<T, E2>
+    where
+        E2: From<E> + From<serde_json::Error> + From<redis::RedisError>,
+    {
+        self.map_err(TxError::into_error)
+    }
+
+    fn unwrap_abort(self) -> Result<T, E> {
+        match self {
+            Ok(value) => Ok(value),
+            Err(TxError::Abort(value)) => Err(value),
+            Err(TxError::AbortMany(values)) => match values.into_iter().next() {
+                Some(value) => Err(value),
+                None => panic!("transaction was aborted without a reason"),
+            },
+            Err(TxError::Serialization(err)) => {
+                panic!("transaction failed to (de)serialize a value: {}", err)
+            }
+            Err(TxError::DbError(err)) => panic!("transaction failed with a redis error: {}", err),
+            Err(TxError::InMulti(err)) => panic!(
+                "transaction started on a connection already in a MULTI, call reset_tx_state first: {}",
+                err
+            ),
+            Err(TxError::PingFailed(err)) => {
+                panic!("transaction found its connection dead: {}", err)
+            }
+            Err(TxError::Timeout) => panic!("transaction timed out"),
+            Err(TxError::Cancelled) => panic!("transaction was cancelled"),
+            Err(TxError::ReplicationTimeout { acked, required }) => panic!(
+                "transaction was only acknowledged by {} of {} replicas",
+                acked, required
+            ),
+            Err(TxError::MaxRetriesExceeded { attempts }) => panic!(
+                "transaction gave up retrying, a watched key changed on all {} attempts",
+                attempts
+            ),
+        }
+    }
+}
+
+/// Turns foreign errors into aborts, so they can be `?`ed out of the body of a transaction.
+///
+/// ```
+/// use redis_utils::{TxError, TxResultExt};
+///
+/// #[derive(Debug, PartialEq)]
+/// enum Rejected {
+///     NotANumber(String),
+/// }
+///
+/// fn parse_body(input: &str) -> Result<u8, TxError<Rejected>> {
+///     let value: u8 = input.parse().or_abort(|_| Rejected::NotANumber(input.to_string()))?;
+///     Ok(value + 1)
+/// }
+///
+/// assert_eq!(parse_body("68").ok(), Some(69));
+/// assert_eq!(parse_body("sixty-eight").err().and_then(TxError::abort), Some(Rejected::NotANumber("sixty-eight".to_string())));
+/// ```
+pub trait TxResultExt<T, E> {
+    /// Maps the error with `f` into the payload of a `TxError::Abort`.
+    fn or_abort<A, F: FnOnce(E) -> A>(self, f: F) -> Result<T, TxError<A>>;
+}
+
+impl<T, E> TxResultExt<T, E> for Result<T, E> {
+    fn or_abort<A, F: FnOnce(E) -> A>(self, f: F) -> Result<T, TxError<A>> {
+        self.map_err(|err| TxError::Abort(f(err)))
+    }
+}
+
+/// A `JsonGetError::KeyMissing` becomes a `TxError::DbError` of kind `TypeError`, naming the key,
+/// `TxError` has no variant of its own for it.
 impl<U> From<JsonGetError> for TxError<U> {
     fn from(err: JsonGetError) -> Self {
         match err {
             JsonGetError::Serialization(err) => TxError::Serialization(err),
             JsonGetError::DbError(err) => TxError::DbError(err),
+            JsonGetError::KeyMissing { key } => TxError::DbError(redis::RedisError::from((
+                redis::ErrorKind::TypeError,
+                "key does not exist",
+                key,
+            ))),
+        }
+    }
+}
+
+/// A `JsonSetError::Validation` becomes a `TxError::Serialization` listing the violations, the value
+/// couldn't be written as its schema says it should.
+impl<U> From<JsonSetError> for TxError<U> {
+    fn from(err: JsonSetError) -> Self {
+        match err {
+            JsonSetError::Serialization(err) => TxError::Serialization(err),
+            JsonSetError::DbError(err) => TxError::DbError(err),
+            JsonSetError::Validation(violations) => {
+                TxError::Serialization(serde::ser::Error::custom(violations.join(", ")))
+            }
         }
     }
 }
 
+/// Only transactions that can't abort (`TxError<Infallible>`) convert back into the errors of the
+/// json helpers, the same way [`TxError::into_error`] folds the other variants.
+///
+/// ```
+/// use std::convert::Infallible;
+/// use redis_utils::converters::JsonGetError;
+/// use redis_utils::TxError;
+///
+/// fn standalone() -> Result<(), JsonGetError> {
+///     let tx_result: Result<(), TxError<Infallible>> = Err(TxError::Timeout);
+///     tx_result?;
+///     Ok(())
+/// }
+///
+/// assert!(matches!(standalone(), Err(JsonGetError::DbError(err)) if err.is_timeout()));
+/// ```
+impl From<TxError<Infallible>> for JsonGetError {
+    fn from(err: TxError<Infallible>) -> Self {
+        err.into_error()
+    }
+}
+
+impl From<TxError<Infallible>> for JsonSetError {
+    fn from(err: TxError<Infallible>) -> Self {
+        err.into_error()
+    }
+}
+
+impl From<Infallible> for JsonGetError {
+    fn from(never: Infallible) -> Self {
+        match never {}
+    }
+}
+
+impl From<Infallible> for JsonSetError {
+    fn from(never: Infallible) -> Self {
+        match never {}
+    }
+}
+
 impl<U> From<redis::RedisError> for TxError<U> {
     fn from(err: redis::RedisError) -> Self {
         TxError::DbError(err)
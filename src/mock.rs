@@ -0,0 +1,171 @@
+//! An in-memory stand-in for a redis connection, shared by the examples of the crate.
+//!
+//! Not part of the API: it's only built with the `test-mock` feature, which the crate's own tests
+//! turn on, and only knows enough of redis for the examples to run without a server.
+
+use redis::aio::ConnectionLike;
+use redis::{Arg, Cmd, ErrorKind, Pipeline, RedisError, RedisFuture, RedisResult, Value};
+use std::collections::{HashMap, VecDeque};
+
+/// Keeps the strings `SET` and `MSET` write for `GET` and `MGET` to read (and `DEL` and `RENAME`
+/// to remove or move), replies `OK` to any other command, and replies with what was queued with
+/// [`reply`](Self::reply) or [`fail`](Self::fail) to the next commands of that name. The queued
+/// commands of an atomic pipeline run at its `EXEC`, unless a watched key is made to change with
+/// [`conflicts`](Self::conflicts).
+#[derive(Default)]
+pub struct MockConnection {
+    /// The names of the commands sent, with `EXEC` standing for an atomic pipeline.
+    pub sent: Vec<String>,
+    /// The arguments of every command run, the name included, those queued in an atomic pipeline
+    /// as of its `EXEC`.
+    pub args: Vec<Vec<String>>,
+    values: HashMap<Vec<u8>, Vec<u8>>,
+    replies: HashMap<String, VecDeque<RedisResult<Value>>>,
+    conflicts: usize,
+}
+
+impl MockConnection {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stores `value` at `key`.
+    pub fn with_value(mut self, key: &str, value: &str) -> Self {
+        self.values.insert(key.into(), value.into());
+        self
+    }
+
+    /// Replies `reply` to the next `name` command not replied to yet.
+    pub fn reply(self, name: &str, reply: Value) -> Self {
+        self.queue(name, Ok(reply))
+    }
+
+    /// Fails the next `name` command not replied to yet with `err`.
+    pub fn fail(self, name: &str, err: RedisError) -> Self {
+        self.queue(name, Err(err))
+    }
+
+    /// Fails the next `count` `EXEC`s as if a watched key changed, running none of their commands.
+    pub fn conflicts(mut self, count: usize) -> Self {
+        self.conflicts += count;
+        self
+    }
+
+    /// The value stored at `key`, if any.
+    pub fn value(&self, key: &str) -> Option<&str> {
+        let value = self.values.get(key.as_bytes())?;
+        Some(std::str::from_utf8(value).expect("a utf-8 value"))
+    }
+
+    fn queue(mut self, name: &str, reply: RedisResult<Value>) -> Self {
+        let queued = self.replies.entry(name.to_ascii_uppercase()).or_default();
+        queued.push_back(reply);
+        self
+    }
+
+    fn run(&mut self, cmd: &Cmd) -> RedisResult<Value> {
+        let args: Vec<Vec<u8>> = cmd
+            .args_iter()
+            .map(|arg| match arg {
+                Arg::Simple(arg) => arg.to_vec(),
+                Arg::Cursor => b"0".to_vec(),
+            })
+            .collect();
+        let name = name(cmd);
+        let lossy = args
+            .iter()
+            .map(|arg| String::from_utf8_lossy(arg).into_owned());
+        self.args.push(lossy.collect());
+
+        if let Some(reply) = self.replies.get_mut(&name).and_then(VecDeque::pop_front) {
+            return reply;
+        }
+        let data = |value: Option<&Vec<u8>>| value.cloned().map_or(Value::Nil, Value::Data);
+        Ok(match &name[..] {
+            "GET" => data(self.values.get(&args[1])),
+            "MGET" => Value::Bulk(
+                args[1..]
+                    .iter()
+                    .map(|key| data(self.values.get(key)))
+                    .collect(),
+            ),
+            "SET" => {
+                self.values.insert(args[1].clone(), args[2].clone());
+                Value::Okay
+            }
+            "MSET" => {
+                for pair in args[1..].chunks(2) {
+                    self.values.insert(pair[0].clone(), pair[1].clone());
+                }
+                Value::Okay
+            }
+            "DEL" => {
+                let removed = args[1..]
+                    .iter()
+                    .filter(|key| self.values.remove(*key).is_some());
+                Value::Int(removed.count() as i64)
+            }
+            "RENAME" => {
+                let value = self.values.remove(&args[1]).ok_or_else(|| {
+                    RedisError::from((
+                        ErrorKind::ResponseError,
+                        "server error",
+                        "no such key".to_string(),
+                    ))
+                })?;
+                self.values.insert(args[2].clone(), value);
+                Value::Okay
+            }
+            _ => Value::Okay,
+        })
+    }
+}
+
+impl ConnectionLike for MockConnection {
+    fn req_packed_command<'a>(&'a mut self, cmd: &'a Cmd) -> RedisFuture<'a, Value> {
+        self.sent.push(name(cmd));
+        let reply = self.run(cmd);
+        Box::pin(async move { reply })
+    }
+
+    fn req_packed_commands<'a>(
+        &'a mut self,
+        pipeline: &'a Pipeline,
+        offset: usize,
+        _: usize,
+    ) -> RedisFuture<'a, Vec<Value>> {
+        // only an atomic pipeline has replies to skip, those of its `MULTI` and queued commands
+        let replies = match offset {
+            0 => pipeline
+                .cmd_iter()
+                .map(|cmd| {
+                    self.sent.push(name(cmd));
+                    self.run(cmd)
+                })
+                .collect(),
+            _ if self.conflicts > 0 => {
+                self.sent.push("EXEC".to_string());
+                self.conflicts -= 1;
+                Ok(vec![Value::Nil])
+            }
+            _ => {
+                self.sent.push("EXEC".to_string());
+                let replies: RedisResult<Vec<Value>> =
+                    pipeline.cmd_iter().map(|cmd| self.run(cmd)).collect();
+                replies.map(|replies| vec![Value::Bulk(replies)])
+            }
+        };
+        Box::pin(async move { replies })
+    }
+
+    fn get_db(&self) -> i64 {
+        0
+    }
+}
+
+fn name(cmd: &Cmd) -> String {
+    match cmd.args_iter().next() {
+        Some(Arg::Simple(name)) => String::from_utf8_lossy(name).to_ascii_uppercase(),
+        _ => String::new(),
+    }
+}
@@ -0,0 +1,96 @@
+//! Key prefixing for multi-tenant keyspaces.
+//!
+//! A [`Namespace`] wraps keys so they're written with its prefix, wherever they're used:
+//!
+//! ```no_run
+//! #[macro_use] extern crate redis_utils;
+//! extern crate redis;
+//!
+//! use redis::RedisResult;
+//! use redis_utils::converters::{JsonGet, PipelineJsonSet};
+//! use redis_utils::namespace::Namespace;
+//! use redis_utils::TxError;
+//!
+//! async fn tenant_demo() -> RedisResult<()> {
+//!     let mut con = redis::Client::open("redis://127.0.0.1/")?.get_async_connection().await?;
+//!     let ns = Namespace::new("tenant:42:");
+//!
+//!     // watches tenant:42:count, reads and writes it
+//!     let tx_result: Result<(), TxError<()>> = tx!(&mut con, pipe, &["count"], {
+//!       let count: u64 = con.json_get(ns.key("count")).await?;
+//!       Ok(pipe.json_set(ns.key("count"), count + 1)?.ignore())
+//!     }, namespace = &ns);
+//!
+//!    Ok(())
+//! }
+//! ```
+//!
+//! The `namespace` option only prefixes the keys the transaction watches, the body has no way of
+//! knowing which arguments of its commands are keys, so it wraps its own keys with the same
+//! `Namespace`.
+//!
+//! A wrapped key prefixes *every* argument its `ToRedisArgs` produces, so a `Vec` or slice of
+//! keys has each of them prefixed, which is what `MGET` or `WATCH` need. Only wrap keys, wrapping
+//! a `(key, value)` tuple would prefix the value too.
+
+use redis::{RedisWrite, ToRedisArgs};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Namespace {
+    prefix: String,
+}
+
+impl Namespace {
+    pub fn new<P: Into<String>>(prefix: P) -> Self {
+        Namespace {
+            prefix: prefix.into(),
+        }
+    }
+
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    /// Wraps `key` so that it's written with the prefix of this namespace.
+    ///
+    /// ```
+    /// use redis::ToRedisArgs;
+    /// use redis_utils::namespace::Namespace;
+    ///
+    /// let ns = Namespace::new("tenant:42:");
+    ///
+    /// assert_eq!(ns.key("user").to_redis_args(), vec![b"tenant:42:user".to_vec()]);
+    /// assert_eq!(
+    ///     ns.key(&["a", "b"]).to_redis_args(),
+    ///     vec![b"tenant:42:a".to_vec(), b"tenant:42:b".to_vec()]
+    /// );
+    /// ```
+    pub fn key<Key: ToRedisArgs>(&self, key: Key) -> Prefixed<'_, Key> {
+        Prefixed {
+            prefix: &self.prefix,
+            key,
+        }
+    }
+}
+
+/// A key written with the prefix of the [`Namespace`] it came from.
+#[derive(Clone, Copy, Debug)]
+pub struct Prefixed<'a, Key> {
+    prefix: &'a str,
+    key: Key,
+}
+
+impl<'a, Key: ToRedisArgs> ToRedisArgs for Prefixed<'a, Key> {
+    fn write_redis_args<W: ?Sized + RedisWrite>(&self, out: &mut W) {
+        for arg in self.key.to_redis_args() {
+            let mut prefixed = Vec::with_capacity(self.prefix.len() + arg.len());
+            prefixed.extend_from_slice(self.prefix.as_bytes());
+            prefixed.extend_from_slice(&arg);
+            out.write_arg(&prefixed);
+        }
+    }
+
+    fn is_single_arg(&self) -> bool {
+        self.key.is_single_arg()
+    }
+}
@@ -0,0 +1,781 @@
+//! Optional settings for a [`tx!`](crate::tx) call.
+//!
+//! Options are given as `name = value` pairs after the body of the transaction, each one calls the
+//! method of the same name on [`TxOptions`]:
+//!
+//! ```no_run
+//! #[macro_use] extern crate redis_utils;
+//! extern crate redis;
+//!
+//! use std::time::Duration;
+//! use redis::{RedisResult, AsyncCommands};
+//! use redis_utils::TxError;
+//!
+//! async fn tx_demo() -> RedisResult<()> {
+//!     let mut con = redis::Client::open("redis://127.0.0.1/")?.get_async_connection().await?;
+//!     let tx_result: Result<u8, TxError<()>> = tx!(&mut con, pipe, &["key1"], {
+//!       let mut value: u8 = con.get("key1").await?;
+//!       value = value + 1;
+//!
+//!       Ok(pipe.set("key1", value))
+//!     }, timeout = Duration::from_secs(2), deadline = Duration::from_secs(10));
+//!
+//!    Ok(())
+//! }
+//! ```
+
+use crate::namespace::Namespace;
+use crate::{TxError, TxOutcome};
+use futures_util::future::{select, Either};
+use futures_util::pin_mut;
+use redis::aio::ConnectionLike;
+use redis::{ErrorKind, FromRedisValue, Pipeline, RedisError, RedisResult, ToRedisArgs, Value};
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+
+#[derive(Clone, Debug, Default)]
+pub struct TxOptions {
+    timeout: Option<Duration>,
+    deadline: Option<(Instant, Duration)>,
+    namespace: Option<Namespace>,
+    ping_first: bool,
+    retry_on_error: Option<Backoff>,
+    retry_strategy: Option<Strategy>,
+    cancel: Option<CancellationToken>,
+    wait: Option<(usize, u64)>,
+    #[cfg(feature = "latency")]
+    clock: Clock,
+}
+
+/// The round trips of a transaction, timed for [`TxLatency`](crate::TxLatency).
+#[derive(Clone, Copy)]
+enum Step {
+    Watch,
+    Exec,
+    Unwatch,
+}
+
+/// The time spent so far on each of the round trips of a transaction, in nanoseconds.
+#[cfg(feature = "latency")]
+#[derive(Debug)]
+struct Clock {
+    started: Instant,
+    spent: [std::sync::atomic::AtomicU64; 3],
+}
+
+#[cfg(feature = "latency")]
+impl Clock {
+    fn spent(&self, step: Step) -> Duration {
+        Duration::from_nanos(self.spent[step as usize].load(std::sync::atomic::Ordering::Relaxed))
+    }
+}
+
+#[cfg(feature = "latency")]
+impl Default for Clock {
+    fn default() -> Self {
+        Clock {
+            started: Instant::now(),
+            spent: Default::default(),
+        }
+    }
+}
+
+#[cfg(feature = "latency")]
+impl Clone for Clock {
+    fn clone(&self) -> Self {
+        let spent = |step| self.spent(step).as_nanos() as u64;
+        Clock {
+            started: self.started,
+            spent: [
+                spent(Step::Watch).into(),
+                spent(Step::Exec).into(),
+                spent(Step::Unwatch).into(),
+            ],
+        }
+    }
+}
+
+/// How the `retry_on_error` option spaces out its attempts: the `n`th retry waits `base * 2^(n-1)`,
+/// capped at `max`, minus up to `jitter` (a fraction between 0 and 1) of that at random so that
+/// clients failing together don't retry together.
+///
+/// ```
+/// #[macro_use] extern crate redis_utils;
+/// extern crate redis;
+///
+/// use std::time::Duration;
+/// use redis::AsyncCommands;
+/// use redis_utils::options::Backoff;
+/// use redis_utils::TxError;
+/// # use redis::{ErrorKind, RedisError};
+/// # use redis_utils::mock::MockConnection;
+/// #
+/// # tokio::runtime::Builder::new_current_thread().enable_time().build().unwrap().block_on(async {
+/// # let resharding = RedisError::from((ErrorKind::TryAgain, "resharding"));
+/// # let mut con = MockConnection::new().fail("WATCH", resharding);
+/// let backoff = Backoff { base: Duration::from_millis(1), ..Backoff::default() };
+/// let tx_result: Result<(), TxError<()>> = tx!(&mut con, pipe, &["key1"], {
+///   Ok(pipe.set("key1", 1).ignore())
+/// }, retry_on_error = backoff);
+///
+/// assert!(tx_result.is_ok());
+/// # });
+/// ```
+#[derive(Clone, Debug)]
+pub struct Backoff {
+    pub base: Duration,
+    pub max: Duration,
+    pub jitter: f64,
+    /// How many times to retry before giving up with the last error.
+    pub retries: u32,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Backoff {
+            base: Duration::from_millis(50),
+            max: Duration::from_secs(2),
+            jitter: 0.5,
+            retries: 3,
+        }
+    }
+}
+
+impl Backoff {
+    /// How long to wait before the `retry`th retry, counting from 1.
+    fn delay(&self, retry: u32) -> Duration {
+        let exponential = exponential(self.base, self.max, retry);
+        exponential.mul_f64(1.0 - self.jitter.clamp(0.0, 1.0) * random())
+    }
+}
+
+/// `base * 2^(retry-1)`, capped at `max`.
+fn exponential(base: Duration, max: Duration, retry: u32) -> Duration {
+    base.checked_mul(1 << retry.saturating_sub(1).min(31))
+        .map_or(max, |delay| delay.min(max))
+}
+
+/// A number between 0 and 1. RandomState is randomly seeded, good enough for spreading retries
+/// without a rand crate.
+fn random() -> f64 {
+    RandomState::new().build_hasher().finish() as f64 / u64::MAX as f64
+}
+
+/// Runs `step` for up to `budget`, failing with `TxError::Timeout` past it.
+async fn timed<R, T, F>(budget: Option<Duration>, step: F) -> Result<R, TxError<T>>
+where
+    F: Future<Output = R>,
+{
+    match budget {
+        None => Ok(step.await),
+        Some(budget) if budget.is_zero() => Err(TxError::Timeout),
+        Some(budget) => tokio::time::timeout(budget, step)
+            .await
+            .map_err(|_| TxError::Timeout),
+    }
+}
+
+/// How the `retry_strategy` option paces the re-runs of a transaction after an optimistic-lock
+/// failure: a watched key changing before the `EXEC`, or the body returning `TxOutcome::Retry`.
+/// Without the option the body is re-run straight away, for as long as it takes.
+///
+/// [`delay`](Self::delay) is asked before every such re-run, with `attempt` counting the failed
+/// attempts in a row (1 before the first re-run). `Some` waits for that long (within the
+/// `deadline`, and unless cancelled), `None` gives up with `TxError::MaxRetriesExceeded`. The
+/// keys are no longer watched by then, giving up leaves nothing to clean up.
+///
+/// ```
+/// #[macro_use] extern crate redis_utils;
+/// extern crate redis;
+///
+/// use std::time::Duration;
+/// use redis::AsyncCommands;
+/// use redis_utils::options::FixedDelay;
+/// use redis_utils::TxError;
+/// # use redis_utils::mock::MockConnection;
+/// #
+/// # tokio::runtime::Builder::new_current_thread().enable_time().build().unwrap().block_on(async {
+/// # let mut con = MockConnection::new().conflicts(3);
+/// let strategy = FixedDelay { delay: Duration::from_millis(1), retries: 2 };
+/// let tx_result: Result<(), TxError<()>> = tx!(&mut con, pipe, &["key1"], {
+///   Ok(pipe.set("key1", 1).ignore())
+/// }, retry_strategy = strategy);
+///
+/// assert!(matches!(tx_result, Err(TxError::MaxRetriesExceeded { attempts: 3 })));
+/// assert_eq!(con.sent, vec!["WATCH", "EXEC", "WATCH", "EXEC", "WATCH", "EXEC"]);
+/// # });
+/// ```
+pub trait RetryStrategy: Send + Sync {
+    /// How long to wait before re-running the transaction after `attempt` failed attempts in a
+    /// row, `None` to give up.
+    fn delay(&self, attempt: u32) -> Option<Duration>;
+}
+
+impl<S: RetryStrategy + ?Sized> RetryStrategy for Arc<S> {
+    fn delay(&self, attempt: u32) -> Option<Duration> {
+        (**self).delay(attempt)
+    }
+}
+
+impl<S: RetryStrategy + ?Sized> RetryStrategy for Box<S> {
+    fn delay(&self, attempt: u32) -> Option<Duration> {
+        (**self).delay(attempt)
+    }
+}
+
+/// Re-runs the transaction straight away, for as long as it takes, like no strategy at all.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Immediate;
+
+impl RetryStrategy for Immediate {
+    fn delay(&self, _: u32) -> Option<Duration> {
+        Some(Duration::ZERO)
+    }
+}
+
+/// Waits `delay` before every re-run, giving up after `retries` of them.
+#[derive(Clone, Copy, Debug)]
+pub struct FixedDelay {
+    pub delay: Duration,
+    pub retries: u32,
+}
+
+impl RetryStrategy for FixedDelay {
+    fn delay(&self, attempt: u32) -> Option<Duration> {
+        (attempt <= self.retries).then_some(self.delay)
+    }
+}
+
+/// Waits anywhere between 0 and `base * 2^(n-1)` (capped at `max`) before the `n`th re-run, at
+/// random, so that clients contending on the same keys drift apart instead of colliding again.
+/// Gives up after `retries` re-runs.
+#[derive(Clone, Copy, Debug)]
+pub struct ExponentialJitter {
+    pub base: Duration,
+    pub max: Duration,
+    pub retries: u32,
+}
+
+impl Default for ExponentialJitter {
+    fn default() -> Self {
+        ExponentialJitter {
+            base: Duration::from_millis(5),
+            max: Duration::from_millis(500),
+            retries: 10,
+        }
+    }
+}
+
+impl RetryStrategy for ExponentialJitter {
+    fn delay(&self, attempt: u32) -> Option<Duration> {
+        let exponential = exponential(self.base, self.max, attempt);
+        (attempt <= self.retries).then(|| exponential.mul_f64(random()))
+    }
+}
+
+/// The strategy of the `retry_strategy` option, shared by the clones of the options.
+#[derive(Clone)]
+struct Strategy(Arc<dyn RetryStrategy>);
+
+impl fmt::Debug for Strategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("RetryStrategy")
+    }
+}
+
+impl TxOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bounds every round trip the transaction makes to redis itself (`WATCH`, `EXEC` and
+    /// `UNWATCH`, and the `PING` of `ping_first`). A round trip that takes longer fails the
+    /// transaction with `TxError::Timeout`. The body, its own requests included, and the waits
+    /// between retries aren't round trips: only the `deadline` bounds them.
+    ///
+    /// ```
+    /// #[macro_use] extern crate redis_utils;
+    /// extern crate redis;
+    ///
+    /// use std::time::Duration;
+    /// use redis_utils::TxError;
+    /// # use redis_utils::mock::MockConnection;
+    ///
+    /// # tokio::runtime::Builder::new_current_thread().enable_time().build().unwrap().block_on(async {
+    /// # let mut con = MockConnection::new();
+    /// // a body slower than the timeout, say one waiting on another service
+    /// let tx_result: Result<(), TxError<()>> = tx!(&mut con, pipe, &["key1"], {
+    ///   tokio::time::sleep(Duration::from_millis(20)).await;
+    ///   Ok(pipe.set("key1", 1).ignore())
+    /// }, timeout = Duration::from_millis(5));
+    /// assert!(tx_result.is_ok());
+    ///
+    /// let tx_result: Result<(), TxError<()>> = tx!(&mut con, pipe, &["key1"], {
+    ///   tokio::time::sleep(Duration::from_millis(20)).await;
+    ///   Ok(pipe.set("key1", 1).ignore())
+    /// }, timeout = Duration::from_millis(5), deadline = Duration::from_millis(10));
+    /// assert!(matches!(tx_result, Err(TxError::Timeout)));
+    /// # });
+    /// ```
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Bounds the whole transaction, including the body and every retry, to `within` from now.
+    /// Once it passes the transaction fails with `TxError::Timeout`.
+    pub fn deadline(mut self, within: Duration) -> Self {
+        self.deadline = Some((Instant::now() + within, within));
+        self
+    }
+
+    /// Prefixes the watched keys with `namespace`, see [`crate::namespace`].
+    pub fn namespace(mut self, namespace: &Namespace) -> Self {
+        self.namespace = Some(namespace.clone());
+        self
+    }
+
+    /// Sends a `PING` before the transaction starts, failing it with `TxError::PingFailed` if the
+    /// connection turns out to be dead, e.g. dropped by a proxy while idle. With `retry_on_error`
+    /// as well, a `PING` failing with a transient error (the connection dropped, a timeout) is
+    /// retried like any other, on the next connection of one that reconnects by itself. Every
+    /// retry of `retry_on_error` pings again, re-runs after a watched key changed don't.
+    ///
+    /// ```
+    /// #[macro_use] extern crate redis_utils;
+    /// extern crate redis;
+    ///
+    /// use std::time::Duration;
+    /// use redis_utils::options::Backoff;
+    /// use redis_utils::TxError;
+    /// # use redis::RedisError;
+    /// # use redis_utils::mock::MockConnection;
+    /// #
+    /// # tokio::runtime::Builder::new_current_thread().enable_time().build().unwrap().block_on(async {
+    /// # let dropped = RedisError::from(std::io::Error::from(std::io::ErrorKind::BrokenPipe));
+    /// // dropped while idle, reconnected by the time of the retry
+    /// # let mut con = MockConnection::new().fail("PING", dropped);
+    /// let backoff = Backoff { base: Duration::from_millis(1), ..Backoff::default() };
+    /// let tx_result: Result<(), TxError<()>> = tx!(&mut con, pipe, &["key1"], {
+    ///   Ok(pipe.set("key1", 1).ignore())
+    /// }, ping_first = true, retry_on_error = backoff);
+    ///
+    /// assert!(tx_result.is_ok());
+    /// assert_eq!(con.sent, vec!["PING", "PING", "WATCH", "EXEC", "UNWATCH"]);
+    ///
+    /// // without `retry_on_error`, the first failed `PING` fails the transaction
+    /// # let dropped = RedisError::from(std::io::Error::from(std::io::ErrorKind::BrokenPipe));
+    /// # let mut con = MockConnection::new().fail("PING", dropped);
+    /// let tx_result: Result<(), TxError<()>> = tx!(&mut con, pipe, &["key1"], {
+    ///   Ok(pipe.set("key1", 1).ignore())
+    /// }, ping_first = true);
+    ///
+    /// assert!(matches!(tx_result, Err(TxError::PingFailed(_))));
+    /// # });
+    /// ```
+    pub fn ping_first(mut self, ping_first: bool) -> Self {
+        self.ping_first = ping_first;
+        self
+    }
+
+    /// Re-runs the whole transaction, waiting as configured by `backoff` in between, when it fails
+    /// with a redis error that's likely transient: a `TRYAGAIN`, `MOVED` / `ASK` redirection, a
+    /// loading or down server, or a dropped connection (which only recovers on a connection that
+    /// reconnects by itself), the `PING` of `ping_first` failing with one included.
+    /// Optimistic-lock failures keep being retried straight away as always, these retries come on
+    /// top of them. Gives up with the last error once `backoff.retries` is reached, or when the
+    /// wait would outlast the `deadline`.
+    pub fn retry_on_error(mut self, backoff: Backoff) -> Self {
+        self.retry_on_error = Some(backoff);
+        self
+    }
+
+    /// Paces the re-runs of the transaction after optimistic-lock failures with `strategy`, and
+    /// gives up when it says so, see [`RetryStrategy`]. Independent of `retry_on_error`, which
+    /// only re-runs after redis errors.
+    pub fn retry_strategy<S: RetryStrategy + 'static>(mut self, strategy: S) -> Self {
+        self.retry_strategy = Some(Strategy(Arc::new(strategy)));
+        self
+    }
+
+    /// Fails the transaction with `TxError::Cancelled` as soon as `token` is cancelled, wherever
+    /// it's at (the body included), e.g. when the request it serves is cancelled. The keys are
+    /// un-watched before it returns.
+    ///
+    /// ```
+    /// #[macro_use] extern crate redis_utils;
+    /// extern crate redis;
+    ///
+    /// use tokio_util::sync::CancellationToken;
+    /// use redis_utils::TxError;
+    /// # use redis_utils::mock::MockConnection;
+    /// #
+    /// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+    /// # let mut con = MockConnection::new();
+    /// let token = CancellationToken::new();
+    ///
+    /// let tx_result: Result<(), TxError<()>> = tx!(&mut con, pipe, &["key1"], {
+    ///   // the request goes away while the body waits on something that never comes
+    ///   token.cancel();
+    ///   std::future::pending::<()>().await;
+    ///   Ok(&mut pipe)
+    /// }, cancel = token.clone());
+    ///
+    /// assert!(matches!(tx_result, Err(TxError::Cancelled)));
+    /// assert_eq!(con.sent, vec!["WATCH", "UNWATCH"]);
+    /// # });
+    /// ```
+    pub fn cancel(mut self, token: CancellationToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    /// Sends a `WAIT replicas timeout_ms` once the transaction has committed, failing it with
+    /// `TxError::ReplicationTimeout` if fewer than `replicas` replicas acknowledged its writes
+    /// within `timeout_ms` milliseconds (`0` waits forever). It's only sent after the `EXEC` that
+    /// went through, never for attempts that are retried, and it isn't bound by the `timeout` and
+    /// `deadline` options.
+    ///
+    /// Either way the writes are committed on the primary by then and aren't retried, so a
+    /// `ReplicationTimeout`, or a `DbError` from `WAIT` itself, means the writes aren't known to be
+    /// replicated, not that they didn't happen.
+    ///
+    /// ```
+    /// #[macro_use] extern crate redis_utils;
+    /// extern crate redis;
+    ///
+    /// use redis_utils::TxError;
+    /// # use redis::Value;
+    /// # use redis_utils::mock::MockConnection;
+    /// #
+    /// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+    /// // the first EXEC fails on a watched key that changed, the second one goes through
+    /// # let mut con = MockConnection::new().conflicts(1).reply("WAIT", Value::Int(1));
+    /// let tx_result: Result<(), TxError<()>> = tx!(&mut con, pipe, &["balance"], {
+    ///   Ok(pipe.set("balance", 100).ignore())
+    /// }, wait = (2, 100));
+    ///
+    /// assert!(matches!(tx_result, Err(TxError::ReplicationTimeout { acked: 1, required: 2 })));
+    /// assert_eq!(con.sent, vec!["WATCH", "EXEC", "WATCH", "EXEC", "UNWATCH", "WAIT"]);
+    /// # });
+    /// ```
+    pub fn wait(mut self, (replicas, timeout_ms): (usize, u64)) -> Self {
+        self.wait = Some((replicas, timeout_ms));
+        self
+    }
+
+    /// How long the next round trip may take, `None` if it's unbounded.
+    fn budget(&self) -> Option<Duration> {
+        match (self.timeout, self.remaining()) {
+            (Some(timeout), Some(remaining)) => Some(timeout.min(remaining)),
+            (timeout, remaining) => timeout.or(remaining),
+        }
+    }
+
+    /// How long until the deadline, `None` without one.
+    fn remaining(&self) -> Option<Duration> {
+        self.deadline
+            .map(|(at, _)| at.saturating_duration_since(Instant::now()))
+    }
+
+    /// Adds the time `request` takes to its `step`, with the `latency` feature.
+    #[cfg(feature = "latency")]
+    async fn clocked<R, F: Future<Output = R>>(&self, step: Step, request: F) -> R {
+        let started = Instant::now();
+        let done = request.await;
+        let spent = started.elapsed().as_nanos() as u64;
+        self.clock.spent[step as usize].fetch_add(spent, std::sync::atomic::Ordering::Relaxed);
+        done
+    }
+
+    #[cfg(not(feature = "latency"))]
+    async fn clocked<R, F: Future<Output = R>>(&self, _: Step, request: F) -> R {
+        request.await
+    }
+
+    /// Where the time went since the options were created, at the start of the transaction.
+    #[cfg(feature = "latency")]
+    #[doc(hidden)]
+    pub fn latency(&self) -> crate::TxLatency {
+        crate::TxLatency {
+            total: self.clock.started.elapsed(),
+            watch: self.clock.spent(Step::Watch),
+            exec: self.clock.spent(Step::Exec),
+            unwatch: self.clock.spent(Step::Unwatch),
+        }
+    }
+
+    async fn round_trip<R, T, F>(&self, request: F) -> Result<R, TxError<T>>
+    where
+        F: Future<Output = RedisResult<R>>,
+    {
+        Ok(self.bounded(request).await??)
+    }
+
+    /// Runs the round trip `request` within the `timeout` and the time left for the transaction,
+    /// unless it's cancelled first.
+    pub(crate) async fn bounded<R, T, F>(&self, request: F) -> Result<R, TxError<T>>
+    where
+        F: Future<Output = R>,
+    {
+        self.guarded(self.budget(), request).await
+    }
+
+    /// Runs `step`, the body or a wait between retries, within the time left for the transaction,
+    /// unless it's cancelled first. The `timeout` is for round trips only.
+    #[doc(hidden)]
+    pub async fn within_deadline<R, T, F>(&self, step: F) -> Result<R, TxError<T>>
+    where
+        F: Future<Output = R>,
+    {
+        self.guarded(self.remaining(), step).await
+    }
+
+    async fn guarded<R, T, F>(&self, budget: Option<Duration>, step: F) -> Result<R, TxError<T>>
+    where
+        F: Future<Output = R>,
+    {
+        let step = timed(budget, step);
+        let token = match &self.cancel {
+            Some(token) if token.is_cancelled() => return Err(TxError::Cancelled),
+            Some(token) => token,
+            None => return step.await,
+        };
+
+        let cancelled = token.cancelled();
+        pin_mut!(step, cancelled);
+        match select(step, cancelled).await {
+            Either::Left((stepped, _)) => stepped,
+            Either::Right(_) => Err(TxError::Cancelled),
+        }
+    }
+
+    /// Turns what the body of the transaction returned into the pipeline to execute, or `None` if
+    /// the transaction should be retried. Unwatches the keys if the body aborted or failed. When it
+    /// failed with a redis error the connection may not be usable anymore, so that `UNWATCH` is
+    /// only best effort and the original error is returned whatever happens to it.
+    #[doc(hidden)]
+    pub async fn settle<'p, C, O, T>(
+        &self,
+        con: &mut C,
+        created: Result<Result<O, TxError<T>>, TxError<T>>,
+    ) -> Result<Option<&'p mut Pipeline>, TxError<T>>
+    where
+        C: ConnectionLike,
+        O: Into<TxOutcome<'p, T>>,
+    {
+        match created.and_then(|created| created).map(Into::into) {
+            Ok(TxOutcome::Commit(pipeline)) => Ok(Some(pipeline)),
+            Ok(TxOutcome::Retry) => {
+                self.unwatch(con).await?;
+                Ok(None)
+            }
+            Ok(TxOutcome::Abort(value)) => {
+                self.unwatch(con).await?;
+                Err(TxError::Abort(value))
+            }
+            Ok(TxOutcome::AbortMany(values)) => {
+                self.unwatch(con).await?;
+                Err(TxError::AbortMany(values))
+            }
+            Err(TxError::DbError(err)) => {
+                let _ = self.unwatch::<_, T>(con).await;
+                Err(TxError::DbError(err))
+            }
+            Err(err @ TxError::Timeout) | Err(err @ TxError::Cancelled) => {
+                self.abandon(con).await;
+                Err(err)
+            }
+            Err(err) => {
+                self.unwatch(con).await?;
+                Err(err)
+            }
+        }
+    }
+
+    /// Whether the failed `attempt` should be retried per the `retry_on_error` option, after
+    /// waiting for as long as it says. `retries` counts the retries so far.
+    #[doc(hidden)]
+    pub async fn recover<R, T>(&self, attempt: &Result<R, TxError<T>>, retries: &mut u32) -> bool {
+        let backoff = match &self.retry_on_error {
+            Some(backoff) if *retries < backoff.retries => backoff,
+            _ => return false,
+        };
+        match attempt {
+            Err(TxError::DbError(err)) | Err(TxError::PingFailed(err)) if is_transient(err) => {}
+            _ => return false,
+        }
+
+        *retries += 1;
+        let delay = backoff.delay(*retries);
+        if self.remaining().is_some_and(|remaining| remaining <= delay) {
+            return false;
+        }
+        self.within_deadline::<_, T, _>(tokio::time::sleep(delay))
+            .await
+            .is_ok()
+    }
+
+    /// Waits before re-running the transaction after an optimistic-lock failure per the
+    /// `retry_strategy` option, or gives up. `conflicts` counts the failures in a row so far.
+    #[doc(hidden)]
+    pub async fn conflicted<T>(&self, conflicts: &mut u32) -> Result<(), TxError<T>> {
+        *conflicts += 1;
+        let strategy = match &self.retry_strategy {
+            Some(Strategy(strategy)) => strategy,
+            None => return Ok(()),
+        };
+        match strategy.delay(*conflicts) {
+            None => Err(TxError::MaxRetriesExceeded {
+                attempts: *conflicts,
+            }),
+            Some(delay) if delay.is_zero() => Ok(()),
+            Some(delay) => self.within_deadline(tokio::time::sleep(delay)).await,
+        }
+    }
+
+    #[doc(hidden)]
+    pub async fn ping<C, T>(&self, con: &mut C) -> Result<(), TxError<T>>
+    where
+        C: ConnectionLike,
+    {
+        if !self.ping_first {
+            return Ok(());
+        }
+        self.bounded(redis::cmd("PING").query_async::<_, ()>(con))
+            .await?
+            .map_err(TxError::PingFailed)
+    }
+
+    #[doc(hidden)]
+    pub async fn watch<C, Key, T>(&self, con: &mut C, keys: Key) -> Result<(), TxError<T>>
+    where
+        C: ConnectionLike,
+        Key: ToRedisArgs,
+    {
+        let mut watch = redis::cmd("WATCH");
+        match &self.namespace {
+            Some(namespace) => watch.arg(namespace.key(keys)),
+            None => watch.arg(keys),
+        };
+
+        let watched = self
+            .clocked(Step::Watch, self.bounded(watch.query_async(con)))
+            .await
+            .and_then(|watched| watched.map_err(TxError::from_watch_error));
+        if let Err(TxError::Timeout) | Err(TxError::Cancelled) = watched {
+            self.abandon(con).await;
+        }
+        watched
+    }
+
+    #[doc(hidden)]
+    pub async fn unwatch<C, T>(&self, con: &mut C) -> Result<(), TxError<T>>
+    where
+        C: ConnectionLike,
+    {
+        let unwatch = redis::cmd("UNWATCH");
+        self.clocked(Step::Unwatch, self.round_trip(unwatch.query_async(con)))
+            .await
+    }
+
+    /// Executes the transaction, `None` if one of the watched keys changed. A failed transaction is
+    /// told apart by `EXEC` replying nil instead of an array, so the replies of the commands
+    /// themselves (nil included) always make it to `R`. An empty pipeline isn't sent at all, it
+    /// commits with no replies.
+    #[doc(hidden)]
+    pub async fn exec<C, R, T>(
+        &self,
+        con: &mut C,
+        pipeline: &Pipeline,
+    ) -> Result<Option<R>, TxError<T>>
+    where
+        C: ConnectionLike,
+        R: FromRedisValue,
+    {
+        if pipeline.cmd_iter().next().is_none() {
+            return Ok(Some(R::from_redis_value(&Value::Bulk(vec![]))?));
+        }
+
+        let executed = self
+            .clocked(Step::Exec, self.round_trip(pipeline.query_async(con)))
+            .await;
+        match executed {
+            Ok(Value::Nil) => Ok(None),
+            Ok(replies) => Ok(Some(R::from_redis_value(&replies)?)),
+            Err(err @ TxError::Timeout) | Err(err @ TxError::Cancelled) => {
+                self.abandon(con).await;
+                Err(err)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Waits for the replicas per the `wait` option once the transaction has `committed`, anything
+    /// else is passed through.
+    #[doc(hidden)]
+    pub async fn replicate<C, R, T>(
+        &self,
+        con: &mut C,
+        committed: Result<R, TxError<T>>,
+    ) -> Result<R, TxError<T>>
+    where
+        C: ConnectionLike,
+    {
+        let (required, timeout_ms) = match (&committed, self.wait) {
+            (Ok(_), Some(wait)) => wait,
+            _ => return committed,
+        };
+
+        let acked: usize = redis::cmd("WAIT")
+            .arg(required)
+            .arg(timeout_ms)
+            .query_async(con)
+            .await?;
+        if acked < required {
+            return Err(TxError::ReplicationTimeout { acked, required });
+        }
+        committed
+    }
+
+    /// Best effort `UNWATCH` after a timeout or a cancellation. It gets the per round trip timeout
+    /// (or the whole deadline if there is none) even if the deadline has already passed, but any
+    /// failure is ignored since the transaction is already failing with `TxError::Timeout` or
+    /// `TxError::Cancelled`.
+    ///
+    /// Note that a timed out request on a plain `redis::aio::Connection` may leave its reply
+    /// unread on the connection, prefer a `MultiplexedConnection` when using timeouts.
+    #[doc(hidden)]
+    pub async fn abandon<C>(&self, con: &mut C)
+    where
+        C: ConnectionLike,
+    {
+        let unwatch = redis::cmd("UNWATCH");
+        let request = unwatch.query_async::<_, ()>(con);
+        match self.timeout.or(self.deadline.map(|(_, within)| within)) {
+            Some(budget) => {
+                let _ = tokio::time::timeout(budget, request).await;
+            }
+            None => {
+                let _ = request.await;
+            }
+        }
+    }
+}
+
+fn is_transient(err: &RedisError) -> bool {
+    match err.kind() {
+        ErrorKind::TryAgain
+        | ErrorKind::Moved
+        | ErrorKind::Ask
+        | ErrorKind::ClusterDown
+        | ErrorKind::MasterDown
+        | ErrorKind::BusyLoadingError => true,
+        _ => err.is_connection_dropped() || err.is_timeout(),
+    }
+}
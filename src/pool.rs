@@ -0,0 +1,154 @@
+//! Transactions over pooled connections.
+//!
+//! The [`tx!`](crate::tx) macro needs a single connection for the whole `WATCH` / `EXEC` cycle.
+//! `run_tx` checks a dedicated connection out of a pool, runs a [`Transaction`] on it, and hands it
+//! back to the pool once it's done, making sure it's un-watched even if the body panics.
+//!
+//! Enable the `deadpool` feature for [deadpool-redis](https://docs.rs/deadpool-redis) pools and the
+//! `bb8` feature for [bb8-redis](https://docs.rs/bb8-redis) pools.
+
+use crate::transaction::{Body, Transaction};
+use crate::{reset_tx_state, TxError};
+use async_trait::async_trait;
+use futures_util::future::{BoxFuture, FutureExt};
+use redis::aio::{Connection, ConnectionLike};
+use redis::{FromRedisValue, Pipeline, ToRedisArgs};
+use std::ops::DerefMut;
+use std::panic::{resume_unwind, AssertUnwindSafe};
+
+/// The ways `run_tx` can fail: either no connection could be checked out of the pool, or the
+/// transaction itself failed.
+pub enum PoolTxError<P, T> {
+    Pool(P),
+    Tx(TxError<T>),
+}
+
+impl<P, T> From<TxError<T>> for PoolTxError<P, T> {
+    fn from(err: TxError<T>) -> Self {
+        PoolTxError::Tx(err)
+    }
+}
+
+/// [`run_tx`] on a connection that's already checked out: a [`Transaction`] watching `keys` on
+/// `con`, which is [reset](reset_tx_state) if the body panics before the panic resumes, so it
+/// never goes back to its pool with keys watched or a `MULTI` open. A body that queues nothing
+/// sends no `MULTI`/`EXEC` and un-watches the keys all the same:
+///
+/// ```
+/// extern crate redis_utils;
+/// extern crate redis;
+///
+/// use futures_util::FutureExt;
+/// use redis::AsyncCommands;
+/// use redis_utils::pool::run_on;
+/// use redis_utils::TxError;
+/// # use redis_utils::mock::MockConnection;
+///
+/// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+/// # let mut con = MockConnection::new().with_value("stock", "5");
+/// let tx_result: Result<(), TxError<()>> = run_on(&mut con, &["stock"], |con, pipe| {
+///     Box::pin(async move {
+///         let stock: u8 = con.get("stock").await?;
+///         // nothing to write when it's already stocked
+///         if stock == 0 {
+///             pipe.set("stock", 10).ignore();
+///         }
+///         Ok(pipe.into())
+///     })
+/// })
+/// .await;
+/// assert!(tx_result.is_ok());
+/// assert_eq!(con.sent, vec!["WATCH", "GET", "UNWATCH"]);
+///
+/// # con.sent.clear();
+/// let tx = run_on::<_, _, (), (), _>(&mut con, &["stock"], |_, _| {
+///     Box::pin(async { panic!("the body panics") })
+/// });
+/// let panicked = std::panic::AssertUnwindSafe(tx).catch_unwind().await;
+/// assert!(panicked.is_err());
+/// assert_eq!(con.sent, vec!["WATCH", "DISCARD", "UNWATCH"]);
+/// # });
+/// ```
+pub async fn run_on<C, Key, T, U, F>(con: &mut C, keys: Key, body: F) -> Result<T, TxError<U>>
+where
+    C: ConnectionLike + Send,
+    Key: ToRedisArgs,
+    T: FromRedisValue,
+    F: for<'a> FnMut(&'a mut C, &'a mut Pipeline) -> BoxFuture<'a, Body<'a, U>>,
+{
+    let run = Transaction::new(&mut *con).watch(keys).run(body);
+    match AssertUnwindSafe(run).catch_unwind().await {
+        Ok(committed) => committed,
+        Err(panic) => {
+            let _ = reset_tx_state(con).await;
+            resume_unwind(panic);
+        }
+    }
+}
+
+/// A pool that can hand out a dedicated connection for the duration of a transaction.
+#[async_trait]
+pub trait TxPool {
+    type Connection: DerefMut<Target = Connection> + Send;
+    type Error;
+
+    async fn checkout(&self) -> Result<Self::Connection, Self::Error>;
+}
+
+#[cfg(feature = "deadpool")]
+#[async_trait]
+impl TxPool for deadpool_redis::Pool {
+    type Connection = deadpool_redis::Connection;
+    type Error = deadpool_redis::PoolError;
+
+    async fn checkout(&self) -> Result<Self::Connection, Self::Error> {
+        self.get().await
+    }
+}
+
+#[cfg(feature = "bb8")]
+#[async_trait]
+impl TxPool for bb8_redis::bb8::Pool<bb8_redis::RedisConnectionManager> {
+    type Connection = bb8_redis::bb8::PooledConnection<'static, bb8_redis::RedisConnectionManager>;
+    type Error = bb8_redis::bb8::RunError<redis::RedisError>;
+
+    async fn checkout(&self) -> Result<Self::Connection, Self::Error> {
+        self.get_owned().await
+    }
+}
+
+/// Runs a transaction on a connection checked out of `pool`. The body is handed the connection
+/// and the atomic pipeline, and is re-run whenever one of the watched `keys` changes before the
+/// pipeline is executed.
+///
+/// ```no_run
+/// extern crate redis_utils;
+/// extern crate redis;
+///
+/// use redis::AsyncCommands;
+/// use redis_utils::pool::{run_tx, PoolTxError, TxPool};
+///
+/// async fn pool_demo<P: TxPool>(pool: &P) {
+///     let tx_result: Result<u8, PoolTxError<_, ()>> = run_tx(pool, &["key1"], |con, pipe| {
+///         Box::pin(async move {
+///             let value: u8 = con.get("key1").await?;
+///             Ok(pipe.set("key1", value + 1).into())
+///         })
+///     })
+///     .await;
+/// }
+/// ```
+pub async fn run_tx<P, Key, T, U, F>(
+    pool: &P,
+    keys: Key,
+    body: F,
+) -> Result<T, PoolTxError<P::Error, U>>
+where
+    P: TxPool,
+    Key: ToRedisArgs,
+    T: FromRedisValue,
+    F: for<'a> FnMut(&'a mut Connection, &'a mut Pipeline) -> BoxFuture<'a, Body<'a, U>>,
+{
+    let mut con = pool.checkout().await.map_err(PoolTxError::Pool)?;
+    Ok(run_on(&mut *con, keys, body).await?)
+}
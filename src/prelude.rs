@@ -0,0 +1,64 @@
+//! Everything a typical user of this crate needs, in one import:
+//!
+//! ```no_run
+//! extern crate redis_utils;
+//! extern crate redis;
+//!
+//! use serde::{Deserialize, Serialize};
+//! use redis::{AsyncCommands, RedisResult};
+//! use redis_utils::prelude::*;
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Session {
+//!     user: String,
+//!     visits: u32,
+//! }
+//!
+//! async fn prelude_demo() -> RedisResult<()> {
+//!     let mut con = redis::Client::open("redis://127.0.0.1/")?.get_async_connection().await?;
+//!     let tx_result: Result<(), TxError<()>> = tx!(&mut con, pipe, &["session"], {
+//!       let mut session: Session = con.json_get("session").await?;
+//!       session.visits += 1;
+//!
+//!       pipe.incr("visits", 1).ignore();
+//!       Ok(pipe.json_set("session", session)?.ignore())
+//!     });
+//!
+//!    Ok(())
+//! }
+//! ```
+//!
+//! It can be glob imported next to `redis::AsyncCommands`: none of the re-exported traits share a
+//! name with anything in `redis`, and their methods are all prefixed (`json_*`, `rj_*`) or named
+//! after what they do differently (`get_typed`, `set_typed`), so method calls stay unambiguous.
+
+pub use crate::batch::JsonBatch;
+pub use crate::cluster::{cluster_tx, JsonClusterGet};
+pub use crate::converters::{
+    ArcCache, HashStruct, JsonCas, JsonCollection, JsonCopy, JsonCounter, JsonGet, JsonList,
+    JsonRead, JsonScan, JsonSet, JsonStream, JsonValue, PipelineJsonSet, Versioned,
+};
+pub use crate::enums::{EnumStore, RedisEnum};
+pub use crate::key::RedisKey;
+pub use crate::namespace::Namespace;
+pub use crate::options::TxOptions;
+pub use crate::stored::JsonStored;
+pub use crate::transaction::Transaction;
+pub use crate::{json_mget_tuple, redis_key_args, tx, tx_with_meta, unwatch, watch};
+pub use crate::{
+    reset_tx_state, watch_more, Aborts, TxCommit, TxError, TxOutcome, TxResult, TxResultExt,
+    TxSuccess,
+};
+
+#[cfg(feature = "client-cache")]
+pub use crate::cache::CachedJsonGet;
+#[cfg(feature = "redisjson")]
+pub use crate::redisjson::RedisJson;
+#[cfg(feature = "schema")]
+pub use crate::schema::JsonSetValidated;
+#[cfg(feature = "derive")]
+pub use crate::stored::RedisJson;
+#[cfg(any(feature = "time", feature = "chrono"))]
+pub use crate::timestamp::JsonSetTimestamped;
+#[cfg(feature = "latency")]
+pub use crate::{tx_with_latency, TxLatency};
@@ -0,0 +1,80 @@
+//! Helpers for the [RedisJSON](https://redis.io/docs/stack/json/) module, enabled with the
+//! `redisjson` feature.
+//!
+//! Unlike the helpers in [`converters`](crate::converters), which store each value as a plain
+//! string holding its json, these store documents natively with `JSON.SET` and can read or write
+//! a nested fragment of a document through a path. The two representations don't mix: a key
+//! written with `json_set` can't be read with `rj_get` and vice versa, so pick one per key.
+
+use crate::converters::{JsonGetError, JsonSetError};
+use async_trait::async_trait;
+use redis::aio::ConnectionLike;
+use redis::ToRedisArgs;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+#[async_trait]
+pub trait RedisJson {
+    async fn rj_get<Key: ToRedisArgs + Send + Sync, Val: DeserializeOwned>(
+        &mut self,
+        key: Key,
+        path: &str,
+    ) -> Result<Val, JsonGetError>;
+    async fn rj_set<Key: ToRedisArgs + Send + Sync, Val: Serialize + Send + Sync>(
+        &mut self,
+        key: Key,
+        path: &str,
+        val: Val,
+    ) -> Result<(), JsonSetError>;
+}
+
+/// ```no_run
+/// extern crate redis_utils;
+/// extern crate redis;
+///
+/// use redis::RedisResult;
+/// use redis_utils::redisjson::RedisJson;
+///
+/// async fn redisjson_demo() -> RedisResult<()> {
+///     let mut con = redis::Client::open("redis://127.0.0.1/")?.get_async_connection().await?;
+///     con.rj_set("person", ".age", 42).await.unwrap();
+///     let age: u8 = con.rj_get("person", ".age").await.unwrap();
+///
+///     Ok(())
+/// }
+/// ```
+#[async_trait]
+impl<C> RedisJson for C
+where
+    C: ConnectionLike + Send + Sync,
+{
+    /// JSON.GET the fragment at `path` -> deserialize it
+    async fn rj_get<Key: ToRedisArgs + Send + Sync, Val: DeserializeOwned>(
+        &mut self,
+        key: Key,
+        path: &str,
+    ) -> Result<Val, JsonGetError> {
+        let val: Option<String> = redis::cmd("JSON.GET")
+            .arg(&key)
+            .arg(path)
+            .query_async(self)
+            .await?;
+        let val = val.ok_or_else(|| JsonGetError::missing(&key))?;
+        Ok(serde_json::from_str(&val)?)
+    }
+
+    /// serialize it to json -> JSON.SET it at `path`
+    async fn rj_set<Key: ToRedisArgs + Send + Sync, Val: Serialize + Send + Sync>(
+        &mut self,
+        key: Key,
+        path: &str,
+        val: Val,
+    ) -> Result<(), JsonSetError> {
+        Ok(redis::cmd("JSON.SET")
+            .arg(key)
+            .arg(path)
+            .arg(serde_json::to_string(&val)?)
+            .query_async(self)
+            .await?)
+    }
+}
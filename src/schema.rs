@@ -0,0 +1,104 @@
+//! Json schema validation on write, enabled with the `schema` feature.
+//!
+//! The schema of a type is derived with [schemars](https://docs.rs/schemars) and compiled once per
+//! type, then reused by every later write of that type.
+
+use crate::converters::JsonSetError;
+use async_trait::async_trait;
+use jsonschema::JSONSchema;
+use redis::aio::ConnectionLike;
+use redis::{AsyncCommands, ToRedisArgs};
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+
+static SCHEMAS: OnceLock<Mutex<HashMap<TypeId, Arc<JSONSchema>>>> = OnceLock::new();
+
+/// Returns the compiled schema of `Val`, compiling it on first use.
+fn schema_of<Val: JsonSchema + 'static>() -> Arc<JSONSchema> {
+    let mut schemas = SCHEMAS
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    schemas
+        .entry(TypeId::of::<Val>())
+        .or_insert_with(|| {
+            let schema = serde_json::to_value(schemars::schema_for!(Val))
+                .expect("schemars produced a schema that isn't valid json");
+            Arc::new(
+                JSONSchema::compile(&schema)
+                    .expect("schemars produced a schema that jsonschema can't compile"),
+            )
+        })
+        .clone()
+}
+
+#[async_trait]
+pub trait JsonSetValidated {
+    async fn json_set_validated<
+        Key: ToRedisArgs + Send + Sync,
+        Val: Serialize + JsonSchema + Send + Sync + 'static,
+    >(
+        &mut self,
+        key: Key,
+        val: Val,
+    ) -> Result<(), JsonSetError>;
+}
+
+/// ```no_run
+/// extern crate redis_utils;
+/// extern crate redis;
+///
+/// use schemars::JsonSchema;
+/// use serde::Serialize;
+/// use redis::RedisResult;
+/// use redis_utils::converters::JsonSetError;
+/// use redis_utils::schema::JsonSetValidated;
+///
+/// #[derive(Serialize, JsonSchema)]
+/// struct Person {
+///     name: String,
+///     age: u8,
+/// }
+///
+/// async fn validated_demo() -> RedisResult<()> {
+///     let mut con = redis::Client::open("redis://127.0.0.1/")?.get_async_connection().await?;
+///     let person = Person { name: "Parth".to_string(), age: 42 };
+///
+///     if let Err(JsonSetError::Validation(violations)) = con.json_set_validated("person", person).await {
+///         println!("refusing to store an invalid person: {:?}", violations);
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+#[async_trait]
+impl<C> JsonSetValidated for C
+where
+    C: ConnectionLike + Send + Sync,
+{
+    /// serialize it to json -> validate it against the schema of `Val` -> set
+    async fn json_set_validated<
+        Key: ToRedisArgs + Send + Sync,
+        Val: Serialize + JsonSchema + Send + Sync + 'static,
+    >(
+        &mut self,
+        key: Key,
+        val: Val,
+    ) -> Result<(), JsonSetError> {
+        let value = serde_json::to_value(&val)?;
+
+        let violations: Vec<String> = match schema_of::<Val>().validate(&value) {
+            Ok(()) => vec![],
+            Err(errors) => errors.map(|error| error.to_string()).collect(),
+        };
+        if !violations.is_empty() {
+            return Err(JsonSetError::Validation(violations));
+        }
+
+        Ok(self.set(key, serde_json::to_string(&value)?).await?)
+    }
+}
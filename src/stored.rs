@@ -0,0 +1,59 @@
+//! Ties types to the keys they're stored under.
+//!
+//! A [`JsonStored`] type lives under `<prefix>:<id>` keys, so that every part of an application
+//! agrees on where, say, users are kept. With the `derive` feature, `#[derive(RedisJson)]`
+//! implements it, taking the prefix from a `#[redis(prefix = "user")]` attribute or the lowercased
+//! type name.
+//!
+//! ```
+//! use redis_utils::stored::JsonStored;
+//!
+//! struct User {
+//!     name: String,
+//! }
+//!
+//! impl JsonStored for User {
+//!     const PREFIX: &'static str = "user";
+//! }
+//!
+//! assert_eq!(User::key(42), "user:42");
+//! ```
+
+use std::fmt::Display;
+
+pub trait JsonStored {
+    const PREFIX: &'static str;
+
+    /// The key the value with `id` is stored under.
+    fn key<Id: Display>(id: Id) -> String {
+        format!("{}:{}", Self::PREFIX, id)
+    }
+}
+
+/// ```no_run
+/// extern crate redis_utils;
+/// extern crate redis;
+///
+/// use serde::{Deserialize, Serialize};
+/// use redis::RedisResult;
+/// use redis_utils::converters::{JsonGet, JsonSet};
+/// use redis_utils::stored::{JsonStored, RedisJson};
+///
+/// #[derive(Serialize, Deserialize, RedisJson)]
+/// #[redis(prefix = "user")]
+/// struct UserProfile {
+///     name: String,
+/// }
+///
+/// async fn stored_demo() -> RedisResult<()> {
+///     let mut con = redis::Client::open("redis://127.0.0.1/")?.get_async_connection().await?;
+///     con.json_set(UserProfile::key(42), UserProfile { name: "parth".to_string() }).await.unwrap();
+///
+///     let profile: UserProfile = con.json_get(UserProfile::key(42)).await.unwrap();
+///     assert_eq!(UserProfile::key(42), "user:42");
+///
+///     Ok(())
+/// }
+/// ```
+#[cfg(feature = "derive")]
+pub use redis_utils_derive::RedisJson;
@@ -0,0 +1,253 @@
+//! Timestamps in json values, enabled with the `time` or the `chrono` feature.
+//!
+//! Values written by different services only agree on their timestamps if they all format them
+//! the same way. [`rfc3339`] (with `time`) and [`rfc3339_chrono`] (with `chrono`) are serde `with`
+//! modules for timestamp fields writing RFC 3339 in UTC (`Z`, with the fractional seconds only
+//! when there are any, without trailing zeros), and reading RFC 3339 with any offset. Both write
+//! the same strings, so services on either crate can share values.
+//! [`JsonSetTimestamped::json_set_with_timestamp`] stamps objects with an `updated_at` field in the
+//! same format as it writes them.
+
+use crate::converters::JsonSetError;
+use async_trait::async_trait;
+use redis::aio::ConnectionLike;
+use redis::{AsyncCommands, ToRedisArgs};
+use serde::Serialize;
+use serde_json::Value;
+#[cfg(feature = "time")]
+use time::format_description::well_known::Rfc3339;
+#[cfg(feature = "time")]
+use time::{OffsetDateTime, UtcOffset};
+
+/// The field [`JsonSetTimestamped::json_set_with_timestamp`] writes.
+pub const UPDATED_AT: &str = "updated_at";
+
+/// Formats `at` the way every helper of this module does: RFC 3339, in UTC.
+#[cfg(feature = "time")]
+pub fn format_rfc3339(at: OffsetDateTime) -> Result<String, time::error::Format> {
+    at.to_offset(UtcOffset::UTC).format(&Rfc3339)
+}
+
+/// [`format_rfc3339`] for a chrono `DateTime`, writing the same string `time` does.
+#[cfg(feature = "chrono")]
+pub fn format_rfc3339_chrono(at: chrono::DateTime<chrono::Utc>) -> String {
+    let formatted = at.to_rfc3339_opts(chrono::SecondsFormat::Nanos, true);
+    // all nine digits of the fraction, trimmed like `time` trims them
+    let trimmed = formatted
+        .trim_end_matches('Z')
+        .trim_end_matches('0')
+        .trim_end_matches('.');
+    format!("{}Z", trimmed)
+}
+
+/// Serializes an `OffsetDateTime` as an RFC 3339 string in UTC, and deserializes one with any
+/// offset, see [`crate::timestamp`].
+///
+/// ```
+/// use serde::{Deserialize, Serialize};
+/// use time::OffsetDateTime;
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct Event {
+///     #[serde(with = "redis_utils::timestamp::rfc3339")]
+///     at: OffsetDateTime,
+///     #[serde(with = "redis_utils::timestamp::rfc3339::option", default)]
+///     acked_at: Option<OffsetDateTime>,
+/// }
+///
+/// let event: Event = serde_json::from_str(r#"{"at":"2022-01-05T14:00:00+02:00"}"#).unwrap();
+/// assert_eq!(
+///     serde_json::to_string(&event).unwrap(),
+///     r#"{"at":"2022-01-05T12:00:00Z","acked_at":null}"#
+/// );
+///
+/// // not only from borrowed strings
+/// let json = serde_json::json!({ "at": "2022-01-05T12:00:00.5Z" });
+/// let event: Event = serde_json::from_value(json).unwrap();
+/// assert_eq!(event.at.nanosecond(), 500_000_000);
+/// ```
+#[cfg(feature = "time")]
+pub mod rfc3339 {
+    use serde::de::Error as _;
+    use serde::ser::Error as _;
+    use serde::{Deserialize, Deserializer, Serializer};
+    use time::format_description::well_known::Rfc3339;
+    use time::OffsetDateTime;
+
+    pub fn serialize<S: Serializer>(at: &OffsetDateTime, serializer: S) -> Result<S::Ok, S::Error> {
+        let formatted = super::format_rfc3339(*at).map_err(S::Error::custom)?;
+        serializer.serialize_str(&formatted)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<OffsetDateTime, D::Error> {
+        let formatted = String::deserialize(deserializer)?;
+        OffsetDateTime::parse(&formatted, &Rfc3339).map_err(D::Error::custom)
+    }
+
+    /// [`rfc3339`](self) for `Option<OffsetDateTime>` fields, `None` being `null`.
+    pub mod option {
+        use serde::{Deserialize, Deserializer, Serialize, Serializer};
+        use time::OffsetDateTime;
+
+        #[derive(Serialize, Deserialize)]
+        #[serde(transparent)]
+        struct Rfc3339(#[serde(with = "super")] OffsetDateTime);
+
+        pub fn serialize<S: Serializer>(
+            at: &Option<OffsetDateTime>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            at.map(Rfc3339).serialize(serializer)
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Option<OffsetDateTime>, D::Error> {
+            let at = Option::<Rfc3339>::deserialize(deserializer)?;
+            Ok(at.map(|Rfc3339(at)| at))
+        }
+    }
+}
+
+/// [`rfc3339`] for a chrono `DateTime<Utc>`, see [`crate::timestamp`].
+///
+/// ```
+/// use chrono::{DateTime, Utc};
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct Event {
+///     #[serde(with = "redis_utils::timestamp::rfc3339_chrono")]
+///     at: DateTime<Utc>,
+///     #[serde(with = "redis_utils::timestamp::rfc3339_chrono::option", default)]
+///     acked_at: Option<DateTime<Utc>>,
+/// }
+///
+/// let event: Event = serde_json::from_str(r#"{"at":"2022-01-05T14:00:00.500+02:00"}"#).unwrap();
+/// assert_eq!(
+///     serde_json::to_string(&event).unwrap(),
+///     r#"{"at":"2022-01-05T12:00:00.5Z","acked_at":null}"#
+/// );
+/// ```
+#[cfg(feature = "chrono")]
+pub mod rfc3339_chrono {
+    use chrono::{DateTime, Utc};
+    use serde::de::Error as _;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(at: &DateTime<Utc>, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&super::format_rfc3339_chrono(*at))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<DateTime<Utc>, D::Error> {
+        let formatted = String::deserialize(deserializer)?;
+        let at = DateTime::parse_from_rfc3339(&formatted).map_err(D::Error::custom)?;
+        Ok(at.with_timezone(&Utc))
+    }
+
+    /// [`rfc3339_chrono`](self) for `Option<DateTime<Utc>>` fields, `None` being `null`.
+    pub mod option {
+        use chrono::{DateTime, Utc};
+        use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+        #[derive(Serialize, Deserialize)]
+        #[serde(transparent)]
+        struct Rfc3339(#[serde(with = "super")] DateTime<Utc>);
+
+        pub fn serialize<S: Serializer>(
+            at: &Option<DateTime<Utc>>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            at.map(Rfc3339).serialize(serializer)
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Option<DateTime<Utc>>, D::Error> {
+            let at = Option::<Rfc3339>::deserialize(deserializer)?;
+            Ok(at.map(|Rfc3339(at)| at))
+        }
+    }
+}
+
+#[async_trait]
+pub trait JsonSetTimestamped {
+    async fn json_set_with_timestamp<Key: ToRedisArgs + Send + Sync, Val: Serialize + Send + Sync>(
+        &mut self,
+        key: Key,
+        val: Val,
+    ) -> Result<(), JsonSetError>;
+}
+
+/// ```no_run
+/// extern crate redis_utils;
+/// extern crate redis;
+///
+/// use serde::{Deserialize, Serialize};
+/// use redis::RedisResult;
+/// use redis_utils::converters::JsonGet;
+/// use redis_utils::timestamp::JsonSetTimestamped;
+///
+/// #[derive(Serialize)]
+/// struct Profile {
+///     bio: String,
+/// }
+///
+/// #[derive(Deserialize)]
+/// struct StoredProfile {
+///     bio: String,
+///     // or a timestamp, read with `rfc3339` / `rfc3339_chrono`
+///     updated_at: String,
+/// }
+///
+/// async fn stamp_demo() -> RedisResult<()> {
+///     let mut con = redis::Client::open("redis://127.0.0.1/")?.get_async_connection().await?;
+///     con.json_set_with_timestamp("profile:1", Profile { bio: "hi".to_string() }).await.unwrap();
+///
+///     let stored: StoredProfile = con.json_get("profile:1").await.unwrap();
+///     println!("last updated at {}", stored.updated_at);
+///
+///     Ok(())
+/// }
+/// ```
+#[async_trait]
+impl<C> JsonSetTimestamped for C
+where
+    C: ConnectionLike + Send + Sync,
+{
+    /// serialize it to json -> set its `updated_at` field to now, in RFC 3339 -> set. Only objects
+    /// can be stamped, anything else fails with `JsonSetError::Serialization` and nothing is
+    /// written; an `updated_at` the value already has is overwritten
+    async fn json_set_with_timestamp<
+        Key: ToRedisArgs + Send + Sync,
+        Val: Serialize + Send + Sync,
+    >(
+        &mut self,
+        key: Key,
+        val: Val,
+    ) -> Result<(), JsonSetError> {
+        use serde::ser::Error as _;
+
+        let mut json = serde_json::to_value(&val)?;
+        let object = match &mut json {
+            Value::Object(object) => object,
+            _ => {
+                return Err(JsonSetError::Serialization(serde_json::Error::custom(
+                    "only json objects can be stamped with an updated_at field",
+                )))
+            }
+        };
+
+        #[cfg(feature = "time")]
+        let now = format_rfc3339(OffsetDateTime::now_utc()).map_err(serde_json::Error::custom)?;
+        #[cfg(not(feature = "time"))]
+        let now = format_rfc3339_chrono(chrono::Utc::now());
+        object.insert(UPDATED_AT.to_string(), Value::String(now));
+
+        Ok(self.set(key, serde_json::to_string(&json)?).await?)
+    }
+}
@@ -0,0 +1,286 @@
+//! A builder for transactions, for when a [`tx!`](crate::tx) macro isn't wanted.
+//!
+//! [`Transaction`] runs the same optimistic-lock loop as the macro with a regular closure: the
+//! watched keys are un-watched when the body aborts or fails, and the body is re-run whenever one
+//! of them changes before the pipeline is executed.
+//!
+//! Since the body is a closure rather than a block pasted into the macro, what it captures and
+//! moves is spelled out, and borrow checker errors point at it rather than at macro internals.
+
+use crate::options::{RetryStrategy, TxOptions};
+use crate::{TxError, TxOutcome};
+use futures_util::future::BoxFuture;
+use redis::aio::ConnectionLike;
+use redis::{FromRedisValue, Pipeline, ToRedisArgs};
+
+/// What the body of a [`Transaction`] resolves to: its [`TxOutcome`], or the error failing it.
+pub type Body<'a, U> = Result<TxOutcome<'a, U>, TxError<U>>;
+
+/// ```no_run
+/// extern crate redis_utils;
+/// extern crate redis;
+///
+/// use std::time::Duration;
+/// use redis::{AsyncCommands, RedisResult};
+/// use redis_utils::options::TxOptions;
+/// use redis_utils::transaction::Transaction;
+/// use redis_utils::{TxError, TxOutcome};
+///
+/// async fn builder_demo() -> RedisResult<()> {
+///     let mut con = redis::Client::open("redis://127.0.0.1/")?.get_async_connection().await?;
+///     let tx_result: Result<u8, TxError<&str>> = Transaction::new(&mut con)
+///         .watch(&["key1"])
+///         .options(TxOptions::new().timeout(Duration::from_secs(2)))
+///         .run(|con, pipe| {
+///             Box::pin(async move {
+///                 let value: u8 = con.get("key1").await?;
+///                 if value == 69 {
+///                     return Err(TxError::Abort("BadNumberFound"));
+///                 }
+///
+///                 Ok(TxOutcome::Commit(pipe.set("key1", value + 1)))
+///             })
+///         })
+///         .await;
+///
+///     Ok(())
+/// }
+/// ```
+pub struct Transaction<'c, C> {
+    con: &'c mut C,
+    keys: Vec<Vec<u8>>,
+    options: TxOptions,
+}
+
+impl<'c, C> Transaction<'c, C>
+where
+    C: ConnectionLike + Send,
+{
+    pub fn new(con: &'c mut C) -> Self {
+        Transaction {
+            con,
+            keys: Vec::new(),
+            options: TxOptions::new(),
+        }
+    }
+
+    /// Adds `keys` to the keys to `WATCH`, can be called several times. Without any the pipeline is
+    /// simply executed atomically.
+    pub fn watch<Key: ToRedisArgs>(mut self, keys: Key) -> Self {
+        self.keys.extend(keys.to_redis_args());
+        self
+    }
+
+    /// Replaces the options of the transaction, see [`crate::options`].
+    pub fn options(mut self, options: TxOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Paces the re-runs after optimistic-lock failures, the `retry_strategy` option of
+    /// [`TxOptions`], see [`RetryStrategy`]. Set it after [`options`](Self::options), which
+    /// replaces every option.
+    ///
+    /// ```
+    /// extern crate redis_utils;
+    /// extern crate redis;
+    ///
+    /// use std::sync::Arc;
+    /// use std::time::Duration;
+    /// use redis::AsyncCommands;
+    /// use redis_utils::options::RetryStrategy;
+    /// use redis_utils::transaction::Transaction;
+    /// use redis_utils::{TxError, TxOutcome};
+    /// # use redis_utils::mock::MockConnection;
+    ///
+    /// /// Leaves contended keys to whoever else is writing them.
+    /// struct NeverRetry;
+    ///
+    /// impl RetryStrategy for NeverRetry {
+    ///     fn delay(&self, _: u32) -> Option<Duration> {
+    ///         None
+    ///     }
+    /// }
+    ///
+    /// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+    /// # let mut con = MockConnection::new().conflicts(1);
+    /// // picked at runtime, say from the configuration of the service
+    /// let strategy: Arc<dyn RetryStrategy> = Arc::new(NeverRetry);
+    ///
+    /// let tx_result: Result<(), TxError<()>> = Transaction::new(&mut con)
+    ///     .watch(&["key1"])
+    ///     .retry_strategy(strategy)
+    ///     .run(|_, pipe| {
+    ///         Box::pin(async move { Ok(TxOutcome::Commit(pipe.set("key1", 1).ignore())) })
+    ///     })
+    ///     .await;
+    ///
+    /// assert!(matches!(tx_result, Err(TxError::MaxRetriesExceeded { attempts: 1 })));
+    /// # });
+    /// ```
+    pub fn retry_strategy<S: RetryStrategy + 'static>(mut self, strategy: S) -> Self {
+        self.options = self.options.retry_strategy(strategy);
+        self
+    }
+
+    /// Runs the transaction. The body is handed the connection and the atomic pipeline to queue
+    /// the writes on, and decides what becomes of the transaction with a [`TxOutcome`], as the
+    /// body of a [`tx!`](crate::tx) does: it commits the pipeline it was handed (`pipe.into()`
+    /// for short), aborts, or re-runs straight away. Returning an error fails it.
+    ///
+    /// ```
+    /// extern crate redis_utils;
+    /// extern crate redis;
+    ///
+    /// use redis::AsyncCommands;
+    /// use redis_utils::transaction::Transaction;
+    /// use redis_utils::{TxError, TxOutcome};
+    /// # use redis_utils::mock::MockConnection;
+    ///
+    /// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+    /// # let mut con = MockConnection::new().with_value("stock", "0");
+    /// let mut runs = 0;
+    /// let tx_result: Result<(), TxError<&str>> = Transaction::new(&mut con)
+    ///     .watch(&["stock"])
+    ///     .run(|con, pipe| {
+    ///         runs += 1;
+    ///         let warm = runs > 1;
+    ///         Box::pin(async move {
+    ///             let stock: u8 = con.get("stock").await?;
+    ///             if !warm {
+    ///                 // say, a cache the body reads along wasn't filled in yet
+    ///                 return Ok(TxOutcome::Retry);
+    ///             }
+    ///             if stock > 0 {
+    ///                 return Ok(TxOutcome::Abort("already stocked"));
+    ///             }
+    ///             Ok(TxOutcome::Commit(pipe.set("stock", 10).ignore()))
+    ///         })
+    ///     })
+    ///     .await;
+    ///
+    /// assert!(tx_result.is_ok());
+    /// assert_eq!(runs, 2);
+    /// assert_eq!(con.sent, vec!["WATCH", "GET", "UNWATCH", "WATCH", "GET", "EXEC", "UNWATCH"]);
+    /// # });
+    /// ```
+    pub async fn run<T, U, F>(self, mut body: F) -> Result<T, TxError<U>>
+    where
+        T: FromRedisValue,
+        F: for<'a> FnMut(&'a mut C, &'a mut Pipeline) -> BoxFuture<'a, Body<'a, U>>,
+    {
+        let Transaction { con, keys, options } = self;
+
+        let mut retries = 0;
+        let committed = loop {
+            let attempt = attempt(con, &keys, &options, &mut body).await;
+            if !options.recover(&attempt, &mut retries).await {
+                break attempt;
+            }
+        };
+        options.replicate(con, committed).await
+    }
+}
+
+/// Runs a transaction watching `keys` on `con`, the closure counterpart of [`tx!`](crate::tx), see
+/// [`Transaction`] for options.
+///
+/// The closure is called again on every retry, so values it hands to the body by move are cloned
+/// out of its captures, and the future it returns only borrows the connection and pipeline:
+///
+/// ```no_run
+/// extern crate redis_utils;
+/// extern crate redis;
+///
+/// use redis::{AsyncCommands, RedisResult};
+/// use redis_utils::transaction::run_tx;
+/// use redis_utils::TxError;
+///
+/// async fn closure_demo(audit_log: String) -> RedisResult<()> {
+///     let mut con = redis::Client::open("redis://127.0.0.1/")?.get_async_connection().await?;
+///     let tx_result: Result<(), TxError<()>> = run_tx(&mut con, &["balance"], |con, pipe| {
+///         let audit_log = audit_log.clone();
+///         Box::pin(async move {
+///             let balance: i64 = con.get("balance").await?;
+///             let entry = tokio::spawn(async move { format!("{}: {}", audit_log, balance) })
+///                 .await
+///                 .unwrap();
+///
+///             pipe.set("balance", balance - 10).ignore().rpush("audit", entry).ignore();
+///             Ok(pipe.into())
+///         })
+///     })
+///     .await;
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn run_tx<C, Key, T, U, F>(con: &mut C, keys: Key, body: F) -> Result<T, TxError<U>>
+where
+    C: ConnectionLike + Send,
+    Key: ToRedisArgs,
+    T: FromRedisValue,
+    F: for<'a> FnMut(&'a mut C, &'a mut Pipeline) -> BoxFuture<'a, Body<'a, U>>,
+{
+    Transaction::new(con).watch(keys).run(body).await
+}
+
+/// One run of the transaction, up to a transient redis error.
+async fn attempt<C, T, U, F>(
+    con: &mut C,
+    keys: &[Vec<u8>],
+    options: &TxOptions,
+    body: &mut F,
+) -> Result<T, TxError<U>>
+where
+    C: ConnectionLike + Send,
+    T: FromRedisValue,
+    F: for<'a> FnMut(&'a mut C, &'a mut Pipeline) -> BoxFuture<'a, Body<'a, U>>,
+{
+    options.ping(con).await?;
+
+    let mut conflicts = 0;
+    loop {
+        if !keys.is_empty() {
+            options.watch(con, keys).await?;
+        }
+
+        let mut pipeline = redis::pipe();
+        pipeline.atomic();
+
+        let configured = &pipeline as *const Pipeline as usize;
+        let created = options.within_deadline(body(con, &mut pipeline)).await;
+        // what the body decided, without the borrows of `con` and `pipeline` it hands back
+        let created = created.map(|created| {
+            created.map(|outcome| match outcome {
+                TxOutcome::Commit(committed) => {
+                    debug_assert!(
+                        committed as *const Pipeline as usize == configured,
+                        "the body of the transaction committed a pipeline other than its own"
+                    );
+                    None
+                }
+                TxOutcome::Abort(value) => Some(TxOutcome::Abort(value)),
+                TxOutcome::AbortMany(values) => Some(TxOutcome::AbortMany(values)),
+                TxOutcome::Retry => Some(TxOutcome::Retry),
+            })
+        });
+        let created = created.map(|created| {
+            created.map(|outcome| outcome.unwrap_or(TxOutcome::Commit(&mut pipeline)))
+        });
+
+        let pipeline = match options.settle(con, created).await? {
+            Some(pipeline) => pipeline,
+            None => {
+                options.conflicted(&mut conflicts).await?;
+                continue;
+            }
+        };
+
+        if let Some(response) = options.exec(con, pipeline).await? {
+            options.unwatch(con).await?;
+            return Ok(response);
+        }
+        options.conflicted(&mut conflicts).await?;
+    }
+}
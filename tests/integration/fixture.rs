@@ -0,0 +1,100 @@
+//! A real redis server for a test to run against.
+//!
+//! [`Redis::start`] uses the server at `REDIS_UTILS_TEST_REDIS_<major>` (e.g.
+//! `REDIS_UTILS_TEST_REDIS_7=redis://127.0.0.1:6380/`) if it's set, and otherwise starts a
+//! `redis:<major>` container with `testcontainers`, removed again when the [`Redis`] is dropped
+//! (a failing test unwinding through it included).
+//! Every key a test writes goes through [`Redis::key`], prefixed with the process and the time it
+//! started at, so runs against a shared server don't see each other's keys and nothing is flushed.
+
+use redis::aio::Connection;
+use redis_utils::TxError;
+use std::fmt::Display;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use testcontainers_modules::redis::{Redis as RedisImage, REDIS_PORT};
+use testcontainers_modules::testcontainers::runners::SyncRunner;
+use testcontainers_modules::testcontainers::{Container, ImageExt};
+
+/// How long a server gets to accept connections.
+const STARTUP: Duration = Duration::from_secs(30);
+
+pub struct Redis {
+    /// The major version the server was asked for, to tell which commands it has.
+    pub major: u8,
+    url: String,
+    /// Held for its `Drop`, which removes the container.
+    _container: Option<Container<RedisImage>>,
+    prefix: String,
+}
+
+impl Redis {
+    /// A redis `major` server, `None` if there's no `REDIS_UTILS_TEST_REDIS_<major>` and no
+    /// container can be started (docker isn't installed, or its daemon isn't running).
+    pub fn start(major: u8) -> Option<Redis> {
+        let started = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let prefix = format!("redis_utils_test:{}:{}:", std::process::id(), started);
+
+        if let Ok(url) = std::env::var(format!("REDIS_UTILS_TEST_REDIS_{}", major)) {
+            return Some(Redis {
+                major,
+                url,
+                _container: None,
+                prefix,
+            });
+        }
+
+        let started = RedisImage::default()
+            .with_tag(major.to_string())
+            .start()
+            .and_then(|container| {
+                let host = container.get_host()?;
+                let port = container.get_host_port_ipv4(REDIS_PORT)?;
+                Ok((container, format!("redis://{}:{}/", host, port)))
+            });
+        let (container, url) = match started {
+            Ok(started) => started,
+            Err(err) => {
+                eprintln!("redis:{}: {}", major, err);
+                return None;
+            }
+        };
+        Some(Redis {
+            major,
+            url,
+            _container: Some(container),
+            prefix,
+        })
+    }
+
+    /// A new connection of its own, waiting for the server to accept it.
+    pub async fn connection(&self) -> Connection {
+        let client = redis::Client::open(self.url.as_str()).unwrap();
+        let started = Instant::now();
+        loop {
+            let err = match client.get_async_connection().await {
+                Ok(mut con) => match redis::cmd("PING").query_async::<_, ()>(&mut con).await {
+                    Ok(()) => return con,
+                    Err(err) => err,
+                },
+                Err(err) => err,
+            };
+            if started.elapsed() > STARTUP {
+                panic!("redis {} at {} isn't up: {}", self.major, self.url, err);
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    }
+
+    /// `name`, prefixed so that it belongs to this run only.
+    pub fn key(&self, name: &str) -> String {
+        format!("{}{}", self.prefix, name)
+    }
+}
+
+/// The value of a transaction that's expected to commit, panicking with the error otherwise.
+pub fn committed<T, E: Display>(tx_result: Result<T, TxError<E>>) -> T {
+    tx_result.unwrap_or_else(|err| panic!("{}", err))
+}
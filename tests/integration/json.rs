@@ -0,0 +1,254 @@
+//! The json helpers of `converters`.
+
+use crate::fixture::{committed, Redis};
+use futures_util::future;
+use redis::aio::Connection;
+use redis::AsyncCommands;
+use redis_utils::converters::{
+    HashStruct, JsonCas, JsonCounter, JsonGet, JsonGetError, JsonList, JsonRead, JsonSet, Versioned,
+};
+use redis_utils::TxError;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct User {
+    name: String,
+    age: u32,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Account {
+    version: u64,
+    balance: i64,
+}
+
+impl Versioned for Account {
+    fn version(&self) -> u64 {
+        self.version
+    }
+
+    fn set_version(&mut self, version: u64) {
+        self.version = version;
+    }
+}
+
+fn ada() -> User {
+    User {
+        name: "ada".to_string(),
+        age: 36,
+    }
+}
+
+pub async fn run(redis: &Redis) {
+    round_trip(redis).await;
+    mget(redis).await;
+    expiry(redis).await;
+    replace(redis).await;
+    field_incr(redis).await;
+    set_if_changed(redis).await;
+    cas_race(redis).await;
+    hash_struct(redis).await;
+    blpop(redis).await;
+    debug_info(redis).await;
+}
+
+async fn round_trip(redis: &Redis) {
+    let mut con = redis.connection().await;
+    let key = redis.key("json:user");
+
+    con.json_set(&key, ada()).await.unwrap();
+    let user: User = con.json_get(&key).await.unwrap();
+    assert_eq!(user, ada());
+
+    let stored: String = con.get(&key).await.unwrap();
+    assert_eq!(stored, r#"{"name":"ada","age":36}"#);
+
+    let missing: Result<User, _> = con.json_get(redis.key("json:nobody")).await;
+    assert!(matches!(missing, Err(JsonGetError::KeyMissing { .. })));
+    let missing: Option<User> = con.maybe_json_get(redis.key("json:nobody")).await.unwrap();
+    assert_eq!(missing, None);
+}
+
+async fn mget(redis: &Redis) {
+    let mut con = redis.connection().await;
+    let keys = vec![redis.key("mget:a"), redis.key("mget:b")];
+
+    con.json_set_multi(&keys, 7u32).await.unwrap();
+    let values: Vec<u32> = con.json_mget(&keys).await.unwrap();
+    assert_eq!(values, vec![7, 7]);
+    let values: Vec<u32> = con.json_mget(&keys[..1]).await.unwrap();
+    assert_eq!(values, vec![7]);
+}
+
+async fn expiry(redis: &Redis) {
+    let mut con = redis.connection().await;
+    let key = redis.key("expiry:session");
+
+    con.json_set(&key, ada()).await.unwrap();
+    assert_eq!(con.json_ttl(&key).await.unwrap(), None);
+
+    // `GETEX`, which every redis 6 image has (6.2 and later)
+    let user: Option<User> = con
+        .json_get_touch(&key, Duration::from_secs(60))
+        .await
+        .unwrap();
+    assert_eq!(user, Some(ada()));
+    let ttl = con.json_ttl(&key).await.unwrap().unwrap();
+    assert!(ttl > Duration::from_secs(50) && ttl <= Duration::from_secs(60));
+
+    let (user, ttl): (User, _) = con.json_get_with_ttl(&key).await.unwrap().unwrap();
+    assert_eq!(user, ada());
+    assert!(ttl.unwrap() <= Duration::from_secs(60));
+    let missing: Option<(User, _)> = con
+        .json_get_with_ttl(redis.key("expiry:nobody"))
+        .await
+        .unwrap();
+    assert!(missing.is_none());
+}
+
+async fn replace(redis: &Redis) {
+    let mut con = redis.connection().await;
+    let key = redis.key("replace:user");
+    con.json_set(&key, ada()).await.unwrap();
+
+    let older = User { age: 37, ..ada() };
+    // `SET ... XX GET`, which every redis 6 image has (6.2 and later)
+    let previous = con.json_replace(&key, older).await.unwrap();
+    assert_eq!(previous, Some(ada()));
+    let missing = con.json_replace(redis.key("replace:nobody"), ada()).await;
+    assert_eq!(missing.unwrap(), None);
+}
+
+async fn field_incr(redis: &Redis) {
+    let mut con = redis.connection().await;
+    let key = redis.key("incr:account");
+
+    con.json_set(&key, serde_json::json!({ "balance": { "available": 10 } }))
+        .await
+        .unwrap();
+    con.expire::<_, ()>(&key, 60).await.unwrap();
+
+    let balance = con
+        .json_field_incr(&key, "balance.available", -2.5)
+        .await
+        .unwrap();
+    assert_eq!(balance, 7.5);
+    let account: serde_json::Value = con.json_get(&key).await.unwrap();
+    assert_eq!(account["balance"]["available"], 7.5);
+    // the script writes with `KEEPTTL`
+    assert!(con.json_ttl(&key).await.unwrap().is_some());
+
+    let missing = con
+        .json_field_incr(redis.key("incr:nobody"), "balance", 1.0)
+        .await;
+    assert!(matches!(missing, Err(JsonGetError::KeyMissing { .. })));
+}
+
+async fn set_if_changed(redis: &Redis) {
+    let mut con = redis.connection().await;
+    let key = redis.key("changed:user");
+
+    let written: Result<bool, TxError<&str>> = con.json_set_if_changed(&key, ada()).await;
+    assert!(committed(written));
+    let written: Result<bool, TxError<&str>> = con.json_set_if_changed(&key, ada()).await;
+    assert!(!committed(written));
+    let written: Result<bool, TxError<&str>> = con
+        .json_set_if_changed(&key, User { age: 37, ..ada() })
+        .await;
+    assert!(committed(written));
+}
+
+async fn cas_race(redis: &Redis) {
+    let mut writer_a = redis.connection().await;
+    let mut writer_b = redis.connection().await;
+    let key = redis.key("cas:account");
+    writer_a
+        .json_set(
+            &key,
+            Account {
+                version: 1,
+                balance: 0,
+            },
+        )
+        .await
+        .unwrap();
+
+    async fn write(con: &mut Connection, key: &str, balance: i64) -> bool {
+        let new_val = Account {
+            version: 1,
+            balance,
+        };
+        let written: Result<bool, TxError<&str>> = con.json_cas(key, 1, new_val).await;
+        committed(written)
+    }
+
+    // both expect version 1, whichever commits first bumps it from under the other
+    let (a, b) = future::join(
+        write(&mut writer_a, &key, 10),
+        write(&mut writer_b, &key, 20),
+    )
+    .await;
+    assert!(a != b, "exactly one writer wins, got {} and {}", a, b);
+
+    let stored: Account = writer_a.json_get(&key).await.unwrap();
+    assert_eq!(stored.version, 2);
+    assert_eq!(stored.balance, if a { 10 } else { 20 });
+}
+
+async fn hash_struct(redis: &Redis) {
+    let mut con = redis.connection().await;
+    let key = redis.key("hash:user");
+
+    redis::cmd("HSET")
+        .arg(&key)
+        .arg("name")
+        .arg("ada")
+        .arg("age")
+        .arg(36)
+        .query_async::<_, ()>(&mut con)
+        .await
+        .unwrap();
+    let user: Option<User> = con.hget_struct(&key).await.unwrap();
+    assert_eq!(user, Some(ada()));
+
+    let missing: Option<User> = con.hget_struct(redis.key("hash:nobody")).await.unwrap();
+    assert_eq!(missing, None);
+}
+
+async fn blpop(redis: &Redis) {
+    let mut con = redis.connection().await;
+    let key = redis.key("queue:jobs");
+
+    // a timeout under a second, which redis 6 and later take
+    let nothing: Option<User> = con
+        .json_blpop(&key, Duration::from_millis(100))
+        .await
+        .unwrap();
+    assert_eq!(nothing, None);
+
+    con.json_rpush(&key, ada()).await.unwrap();
+    let job: Option<User> = con.json_blpop(&key, Duration::from_secs(1)).await.unwrap();
+    assert_eq!(job, Some(ada()));
+}
+
+async fn debug_info(redis: &Redis) {
+    let mut con = redis.connection().await;
+
+    let key = redis.key("info:user");
+    con.json_set(&key, ada()).await.unwrap();
+    let info = con.json_debug_info(&key).await.unwrap();
+    assert_eq!(info.kind, "string");
+    assert_eq!(info.encoding.as_deref(), Some("embstr"));
+    assert_eq!(info.len, r#"{"name":"ada","age":36}"#.len());
+    assert_eq!(info.ttl, None);
+    assert!(info.valid_json);
+
+    let key = redis.key("info:count");
+    con.json_set(&key, 42u32).await.unwrap();
+    let info = con.json_debug_info(&key).await.unwrap();
+    assert_eq!(info.encoding.as_deref(), Some("int"));
+
+    let info = con.json_debug_info(redis.key("info:nobody")).await.unwrap();
+    assert!(!info.exists());
+}
@@ -0,0 +1,46 @@
+//! The helpers against real redis servers, one suite per major version, built with the
+//! `test-integration` feature:
+//!
+//! ```sh
+//! cargo test --features test-integration --test integration
+//! ```
+//!
+//! The servers come from [`fixture::Redis`]. With the feature on nothing is skipped: the suite of a
+//! version fails when there's neither a server given for it nor docker to start one. Everything
+//! runs over RESP2, the only protocol redis-rs 0.21 speaks.
+//!
+//! New scenarios go in the module they exercise, as an `async fn(&Redis)` called from its `run`.
+
+mod fixture;
+mod json;
+mod transactions;
+
+use fixture::Redis;
+
+fn suite(major: u8) {
+    let redis = Redis::start(major).unwrap_or_else(|| {
+        panic!(
+            "no redis {} to run against: set REDIS_UTILS_TEST_REDIS_{} or start docker",
+            major, major
+        )
+    });
+
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap()
+        .block_on(async {
+            json::run(&redis).await;
+            transactions::run(&redis).await;
+        });
+}
+
+#[test]
+fn redis_6() {
+    suite(6)
+}
+
+#[test]
+fn redis_7() {
+    suite(7)
+}
@@ -0,0 +1,160 @@
+//! `tx!` and `tx_with_meta!`, on their own and contended.
+
+use crate::fixture::{committed, Redis};
+use futures_util::future;
+use redis::aio::Connection;
+use redis_utils::converters::{JsonGet, JsonSet, PipelineJsonSet};
+use redis_utils::options::FixedDelay;
+use redis_utils::{tx, tx_with_meta, TxError, TxSuccess};
+use std::time::Duration;
+
+pub async fn run(redis: &Redis) {
+    commit(redis).await;
+    abort_unwatches(redis).await;
+    contention(redis).await;
+    contention_gives_up(redis).await;
+    reads_keep_the_watch(redis).await;
+    concurrent_increments(redis).await;
+}
+
+async fn increment(
+    con: &mut Connection,
+    key: &str,
+) -> Result<TxSuccess<()>, TxError<&'static str>> {
+    tx_with_meta!(con, pipe, &[key], {
+        let count: u32 = con.json_get(key).await?;
+        Ok(pipe.json_set(key, count + 1)?.ignore())
+    })
+}
+
+async fn commit(redis: &Redis) {
+    let mut con = redis.connection().await;
+    let key = redis.key("tx:count");
+    con.json_set(&key, 1u32).await.unwrap();
+
+    let committed = committed(increment(&mut con, &key).await);
+    assert_eq!(committed.attempts, 1);
+    let count: u32 = con.json_get(&key).await.unwrap();
+    assert_eq!(count, 2);
+}
+
+async fn abort_unwatches(redis: &Redis) {
+    let mut con = redis.connection().await;
+    let mut other = redis.connection().await;
+    let aborted = redis.key("tx:aborted");
+    let count = redis.key("tx:unwatched");
+    con.json_set(&aborted, 1u32).await.unwrap();
+    con.json_set(&count, 1u32).await.unwrap();
+
+    let tx_result: Result<(), TxError<&str>> = tx!(&mut con, pipe, &[&aborted], {
+        Err::<&mut redis::Pipeline, _>(TxError::Abort("not today"))
+    });
+    assert!(matches!(tx_result, Err(TxError::Abort("not today"))));
+
+    // had `aborted` stayed watched, this write would fail the next transaction on `con`
+    other.json_set(&aborted, 2u32).await.unwrap();
+    let committed = committed(increment(&mut con, &count).await);
+    assert_eq!(committed.attempts, 1);
+}
+
+async fn contention(redis: &Redis) {
+    let mut con = redis.connection().await;
+    let mut other = redis.connection().await;
+    let key = redis.key("tx:contended");
+    con.json_set(&key, 1u32).await.unwrap();
+
+    let mut interfere = true;
+    let tx_result: Result<TxSuccess<()>, TxError<&'static str>> =
+        tx_with_meta!(&mut con, pipe, &[&key], {
+            let count: u32 = con.json_get(&key).await?;
+            // another client writes the key between the read and the `EXEC` of the first attempt
+            if std::mem::replace(&mut interfere, false) {
+                other.json_set(&key, 10u32).await?;
+            }
+            Ok(pipe.json_set(&key, count + 1)?.ignore())
+        });
+
+    let committed = committed(tx_result);
+    assert_eq!(committed.attempts, 2);
+    assert!(committed.retried);
+    let count: u32 = con.json_get(&key).await.unwrap();
+    assert_eq!(count, 11);
+}
+
+async fn contention_gives_up(redis: &Redis) {
+    let mut con = redis.connection().await;
+    let mut other = redis.connection().await;
+    let key = redis.key("tx:given_up");
+    con.json_set(&key, 1u32).await.unwrap();
+
+    let strategy = FixedDelay {
+        delay: Duration::from_millis(10),
+        retries: 1,
+    };
+    let tx_result: Result<(), TxError<&str>> = tx!(
+        &mut con,
+        pipe,
+        &[&key],
+        {
+            let count: u32 = con.json_get(&key).await?;
+            // every attempt loses to another client
+            other.json_set(&key, count + 10).await?;
+            Ok(pipe.json_set(&key, count + 1)?.ignore())
+        },
+        retry_strategy = strategy
+    );
+
+    assert!(matches!(
+        tx_result,
+        Err(TxError::MaxRetriesExceeded { attempts: 2 })
+    ));
+    let count: u32 = con.json_get(&key).await.unwrap();
+    assert_eq!(count, 21);
+}
+
+async fn reads_keep_the_watch(redis: &Redis) {
+    let mut con = redis.connection().await;
+    let mut other = redis.connection().await;
+    let key = redis.key("tx:read_with_ttl");
+    con.json_set(&key, 1u32).await.unwrap();
+
+    let mut interfere = true;
+    let tx_result: Result<TxSuccess<()>, TxError<&'static str>> =
+        tx_with_meta!(&mut con, pipe, &[&key], {
+            // a pipelined read in the body, which mustn't drop the watch on `key`
+            let (count, _ttl): (u32, _) = con.json_get_with_ttl(&key).await?.unwrap();
+            if std::mem::replace(&mut interfere, false) {
+                other.json_set(&key, 10u32).await?;
+            }
+            Ok(pipe.json_set(&key, count + 1)?.ignore())
+        });
+
+    assert_eq!(committed(tx_result).attempts, 2);
+    let count: u32 = con.json_get(&key).await.unwrap();
+    assert_eq!(count, 11);
+}
+
+async fn concurrent_increments(redis: &Redis) {
+    const INCREMENTS: u32 = 25;
+
+    let mut first = redis.connection().await;
+    let mut second = redis.connection().await;
+    let key = redis.key("tx:concurrent");
+    first.json_set(&key, 0u32).await.unwrap();
+
+    async fn increments(con: &mut Connection, key: &str) -> u32 {
+        let mut attempts = 0;
+        for _ in 0..INCREMENTS {
+            attempts += committed(increment(con, key).await).attempts;
+        }
+        attempts
+    }
+
+    let (first_attempts, second_attempts) =
+        future::join(increments(&mut first, &key), increments(&mut second, &key)).await;
+
+    // every increment lands exactly once, however often the two got in each other's way
+    let count: u32 = first.json_get(&key).await.unwrap();
+    assert_eq!(count, 2 * INCREMENTS);
+    assert!(first_attempts + second_attempts >= 2 * INCREMENTS);
+}